@@ -0,0 +1,98 @@
+//! Times each [`IoStrategy`] on files of several sizes, and marks the one
+//! [`IoStrategy::Auto`] picks with the default full-load limit.
+//!
+//! ```text
+//! cargo run --release -p zhash-core --example io_bench [directory]
+//! ```
+//!
+//! Files are written to `directory` (default: the system temporary
+//! directory) and removed afterwards. Each run is timed best of three, so the
+//! figures are for files already in the page cache; run it on the target
+//! disk after dropping caches to measure cold reads.
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use zhash_core::hash::hash_file;
+use zhash_core::{HashAlgo, IoOptions, IoStrategy, MmapUnchecked};
+
+const STRATEGIES: [IoStrategy; 3] = [IoStrategy::Read, IoStrategy::Mmap, IoStrategy::Whole];
+
+/// Label, size of each file, number of files.
+const CASES: [(&str, u64, usize); 6] = [
+    ("4 KiB", 4 << 10, 2000),
+    ("1 MiB", 1 << 20, 200),
+    ("16 MiB", 16 << 20, 16),
+    ("64 MiB", 64 << 20, 4),
+    ("256 MiB", 256 << 20, 1),
+    ("1 GiB", 1 << 30, 1),
+];
+
+fn main() -> io::Result<()> {
+    let base = std::env::args_os().nth(1).map(PathBuf::from).unwrap_or_else(std::env::temp_dir);
+    let dir = base.join(format!("zhash-io-bench-{}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+    let result = run(&dir);
+    fs::remove_dir_all(&dir)?;
+    result
+}
+
+fn run(dir: &Path) -> io::Result<()> {
+    // SAFETY: the files are private to this run and never truncated
+    let defaults = IoOptions { mmap_unchecked: Some(unsafe { MmapUnchecked::new() }), ..IoOptions::default() };
+    print!("{:<8}", "size");
+    for strategy in STRATEGIES {
+        print!("{:>14}", format!("{strategy:?}").to_lowercase());
+    }
+    println!("{:>10}", "auto");
+
+    for (label, size, count) in CASES {
+        let files = (0..count)
+            .map(|i| {
+                let path = dir.join(format!("{label}-{i}").replace(' ', ""));
+                write_random(&path, size, i as u64)?;
+                Ok(path)
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        print!("{label:<8}");
+        for strategy in STRATEGIES {
+            let io = IoOptions { strategy, ..defaults };
+            let best = (0..3).map(|_| time(&files, io)).collect::<io::Result<Vec<_>>>()?.into_iter().min();
+            let secs = best.unwrap_or_default().as_secs_f64();
+            let mib = (size * count as u64) as f64 / (1 << 20) as f64;
+            print!("{:>14}", format!("{:.0} MiB/s", mib / secs));
+        }
+        println!("{:>10}", format!("{:?}", defaults.strategy_for(size)).to_lowercase());
+        for path in files {
+            fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
+
+fn time(files: &[PathBuf], io: IoOptions) -> io::Result<Duration> {
+    let start = Instant::now();
+    for path in files {
+        hash_file(path, HashAlgo::Xxh3, io)?;
+    }
+    Ok(start.elapsed())
+}
+
+/// Fills `path` with `size` pseudo-random bytes (xorshift), cheap to produce.
+fn write_random(path: &Path, size: u64, seed: u64) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    let mut state = seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1;
+    let mut written = 0;
+    while written < size {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        let bytes = state.to_le_bytes();
+        let len = (size - written).min(8) as usize;
+        out.write_all(&bytes[..len])?;
+        written += len as u64;
+    }
+    out.flush()
+}
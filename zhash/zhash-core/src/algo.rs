@@ -0,0 +1,226 @@
+use std::path::Path;
+
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+use xxhash_rust::xxh3::Xxh3;
+
+use crate::tree::{TreeHasher, tree_hash_bytes};
+
+/// Hash algorithms understood by the generator and the verifiers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum HashAlgo {
+    Crc32,
+    Md5,
+    Xxh3,
+    /// 128-bit XXH3, for trees large enough that 64-bit collisions matter.
+    Xxh128,
+    /// Legacy; kept for interoperability with existing `sha1sum` lists.
+    Sha1,
+    Sha256,
+    Sha512,
+    Blake3,
+    /// XXH3 tree hash, whose leaves are hashed in parallel, for very large
+    /// files; see [`TreeHasher`]. Its digests differ from plain XXH3 ones.
+    Xxh3Tree,
+}
+
+impl HashAlgo {
+    /// Every supported algorithm, in manifest auto-detection order.
+    pub const ALL: [HashAlgo; 9] = [
+        HashAlgo::Xxh3,
+        HashAlgo::Xxh128,
+        HashAlgo::Md5,
+        HashAlgo::Crc32,
+        HashAlgo::Sha256,
+        HashAlgo::Sha512,
+        HashAlgo::Sha1,
+        HashAlgo::Blake3,
+        HashAlgo::Xxh3Tree,
+    ];
+
+    /// Conventional manifest filename for this algorithm (`CRC.xxhash3`, ...).
+    pub fn manifest_name(self) -> &'static str {
+        match self {
+            HashAlgo::Crc32 => "CRC.crc32",
+            HashAlgo::Md5 => "CRC.md5",
+            HashAlgo::Xxh3 => "CRC.xxhash3",
+            HashAlgo::Xxh128 => "CRC.xxh128",
+            HashAlgo::Sha1 => "CRC.sha1",
+            HashAlgo::Sha256 => "CRC.sha256",
+            HashAlgo::Sha512 => "CRC.sha512",
+            HashAlgo::Blake3 => "CRC.blake3",
+            HashAlgo::Xxh3Tree => "CRC.xxh3tree",
+        }
+    }
+
+    /// Short lowercase identifier (`xxh3`, `sha256`, ...), as used on the
+    /// command line and in multi-algorithm manifest headers.
+    pub fn name(self) -> &'static str {
+        match self {
+            HashAlgo::Crc32 => "crc32",
+            HashAlgo::Md5 => "md5",
+            HashAlgo::Xxh3 => "xxh3",
+            HashAlgo::Xxh128 => "xxh128",
+            HashAlgo::Sha1 => "sha1",
+            HashAlgo::Sha256 => "sha256",
+            HashAlgo::Sha512 => "sha512",
+            HashAlgo::Blake3 => "blake3",
+            HashAlgo::Xxh3Tree => "xxh3-tree",
+        }
+    }
+
+    /// Inverse of [`HashAlgo::name`], ignoring case.
+    pub fn from_name(name: &str) -> Option<HashAlgo> {
+        HashAlgo::ALL.into_iter().find(|algo| algo.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Algorithm whose conventional manifest filename is `name`.
+    pub fn from_manifest_name(name: &str) -> Option<HashAlgo> {
+        HashAlgo::ALL.into_iter().find(|algo| algo.manifest_name() == name)
+    }
+
+    /// Algorithm whose manifests use the extension of `path` (`.xxhash3`, `.md5`, `.crc32`).
+    pub fn from_manifest_path(path: &Path) -> Option<HashAlgo> {
+        let ext = path.extension()?.to_str()?;
+        HashAlgo::ALL
+            .into_iter()
+            .find(|algo| algo.manifest_name().rsplit('.').next() == Some(ext))
+    }
+
+    /// Algorithm most likely to have produced `digest`, judging by its length.
+    ///
+    /// 32-digit digests are taken as MD5, which is far more common in the
+    /// wild than XXH3-128, and 64-digit ones as SHA-256 rather than BLAKE3.
+    pub fn from_digest(digest: &str) -> Option<HashAlgo> {
+        if !digest.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        match digest.len() {
+            8 => Some(HashAlgo::Crc32),
+            16 => Some(HashAlgo::Xxh3),
+            32 => Some(HashAlgo::Md5),
+            40 => Some(HashAlgo::Sha1),
+            64 => Some(HashAlgo::Sha256),
+            128 => Some(HashAlgo::Sha512),
+            _ => None,
+        }
+    }
+
+    /// Algorithm named by a BSD-style tag (`SHA256 (file) = ...`), ignoring case.
+    ///
+    /// Tags are the same as [`HashAlgo::display_name`], which matches what
+    /// `xxhsum --tag`, `shasum --tag` and BSD `md5` write.
+    pub fn from_tag(tag: &str) -> Option<HashAlgo> {
+        HashAlgo::ALL.into_iter().find(|algo| algo.display_name().eq_ignore_ascii_case(tag))
+    }
+
+    /// Human-readable name, as shown in menus and reports and used as BSD tag.
+    pub fn display_name(self) -> &'static str {
+        match self {
+            HashAlgo::Crc32 => "CRC32",
+            HashAlgo::Md5 => "MD5",
+            HashAlgo::Xxh3 => "XXH3",
+            HashAlgo::Xxh128 => "XXH128",
+            HashAlgo::Sha1 => "SHA1",
+            HashAlgo::Sha256 => "SHA256",
+            HashAlgo::Sha512 => "SHA512",
+            HashAlgo::Blake3 => "BLAKE3",
+            HashAlgo::Xxh3Tree => "XXH3-TREE",
+        }
+    }
+
+    /// Whether this is a tree hash, whose digests look like those of the
+    /// plain algorithm and must therefore always be labeled in manifests.
+    pub fn is_tree(self) -> bool {
+        self == HashAlgo::Xxh3Tree
+    }
+
+    /// Hashes an in-memory buffer in one shot.
+    pub fn hash_bytes(self, data: &[u8]) -> String {
+        match self {
+            HashAlgo::Crc32 => format!("{:08x}", crc32fast::hash(data)),
+            HashAlgo::Md5 => format!("{:x}", md5::compute(data)),
+            HashAlgo::Xxh3 => format!("{:016x}", xxhash_rust::xxh3::xxh3_64(data)),
+            HashAlgo::Xxh128 => format!("{:032x}", xxhash_rust::xxh3::xxh3_128(data)),
+            HashAlgo::Sha1 => format!("{:x}", Sha1::digest(data)),
+            HashAlgo::Sha256 => format!("{:x}", Sha256::digest(data)),
+            HashAlgo::Sha512 => format!("{:x}", Sha512::digest(data)),
+            HashAlgo::Blake3 => blake3::hash(data).to_hex().to_string(),
+            HashAlgo::Xxh3Tree => tree_hash_bytes(data),
+        }
+    }
+
+    /// Compares an expected digest from a manifest with a computed one.
+    ///
+    /// Case is ignored; for CRC32 a `0x` prefix and leading zeros are too,
+    /// since hand-written CRC lists frequently drop them.
+    pub fn digests_match(self, expected: &str, actual: &str) -> bool {
+        match self {
+            HashAlgo::Crc32 => {
+                let normalize = |s: &str| s.trim_start_matches("0x").trim_start_matches('0').to_lowercase();
+                normalize(expected) == normalize(actual)
+            }
+            _ => expected.eq_ignore_ascii_case(actual),
+        }
+    }
+}
+
+/// Streaming hasher for any [`HashAlgo`].
+pub enum Hasher {
+    Crc32(crc32fast::Hasher),
+    Md5(md5::Context),
+    Xxh3(Box<Xxh3>),
+    Xxh128(Box<Xxh3>),
+    Sha1(Box<Sha1>),
+    Sha256(Box<Sha256>),
+    Sha512(Box<Sha512>),
+    Blake3(Box<blake3::Hasher>),
+    Xxh3Tree(Box<TreeHasher>),
+}
+
+impl Hasher {
+    pub fn new(algo: HashAlgo) -> Self {
+        match algo {
+            HashAlgo::Crc32 => Hasher::Crc32(crc32fast::Hasher::new()),
+            HashAlgo::Md5 => Hasher::Md5(md5::Context::new()),
+            HashAlgo::Xxh3 => Hasher::Xxh3(Box::new(Xxh3::new())),
+            HashAlgo::Xxh128 => Hasher::Xxh128(Box::new(Xxh3::new())),
+            HashAlgo::Sha1 => Hasher::Sha1(Box::new(Sha1::new())),
+            HashAlgo::Sha256 => Hasher::Sha256(Box::new(Sha256::new())),
+            HashAlgo::Sha512 => Hasher::Sha512(Box::new(Sha512::new())),
+            HashAlgo::Blake3 => Hasher::Blake3(Box::new(blake3::Hasher::new())),
+            HashAlgo::Xxh3Tree => Hasher::Xxh3Tree(Box::default()),
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Crc32(h) => h.update(data),
+            Hasher::Md5(h) => h.consume(data),
+            Hasher::Xxh3(h) | Hasher::Xxh128(h) => h.update(data),
+            Hasher::Sha1(h) => h.update(data),
+            Hasher::Sha256(h) => h.update(data),
+            Hasher::Sha512(h) => h.update(data),
+            Hasher::Blake3(h) => {
+                h.update(data);
+            }
+            Hasher::Xxh3Tree(h) => h.update(data),
+        }
+    }
+
+    /// Consumes the hasher and returns the lowercase hex digest.
+    pub fn finalize(self) -> String {
+        match self {
+            Hasher::Crc32(h) => format!("{:08x}", h.finalize()),
+            Hasher::Md5(h) => format!("{:032x}", h.finalize()),
+            Hasher::Xxh3(h) => format!("{:016x}", h.digest()),
+            Hasher::Xxh128(h) => format!("{:032x}", h.digest128()),
+            Hasher::Sha1(h) => format!("{:x}", h.finalize()),
+            Hasher::Sha256(h) => format!("{:x}", h.finalize()),
+            Hasher::Sha512(h) => format!("{:x}", h.finalize()),
+            Hasher::Blake3(h) => h.finalize().to_hex().to_string(),
+            Hasher::Xxh3Tree(h) => h.finalize(),
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::sidecar::{BACKUP_SUFFIX, TMP_SUFFIX, with_suffix};
+
+/// A file written under a temporary name next to its target and renamed over
+/// it by [`AtomicFile::commit`], so that readers see either the previous
+/// version or the complete new one, even after a crash.
+///
+/// Dropping it without committing removes the temporary file and leaves the
+/// target untouched.
+pub struct AtomicFile {
+    path: PathBuf,
+    out: BufWriter<File>,
+    committed: bool,
+}
+
+impl AtomicFile {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let out = BufWriter::new(File::create(tmp_path(path))?);
+        Ok(Self { path: path.to_path_buf(), out, committed: false })
+    }
+
+    /// Flushes and fsyncs the new contents, then renames them over the
+    /// target. With `backup`, the previous version is kept as
+    /// [`backup_path`].
+    pub fn commit(mut self, backup: bool) -> io::Result<()> {
+        self.out.flush()?;
+        self.out.get_ref().sync_all()?;
+        if backup && self.path.exists() {
+            let backup = backup_path(&self.path);
+            match fs::remove_file(&backup) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+            // A hard link keeps the target in place until the rename replaces it
+            fs::hard_link(&self.path, &backup).or_else(|_| fs::copy(&self.path, &backup).map(drop))?;
+        }
+        fs::rename(tmp_path(&self.path), &self.path)?;
+        self.committed = true;
+        sync_parent(&self.path)
+    }
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.out.write(buf)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.out.write_all(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = fs::remove_file(tmp_path(&self.path));
+        }
+    }
+}
+
+/// Writes `path` atomically with `write`; see [`AtomicFile`].
+pub fn write_atomically(
+    path: &Path,
+    backup: bool,
+    write: impl FnOnce(&mut AtomicFile) -> io::Result<()>,
+) -> io::Result<()> {
+    let mut file = AtomicFile::create(path)?;
+    write(&mut file)?;
+    file.commit(backup)
+}
+
+/// Temporary name [`AtomicFile`] writes `path` under: `CRC.xxhash3.tmp`.
+pub fn tmp_path(path: &Path) -> PathBuf {
+    with_suffix(path, TMP_SUFFIX)
+}
+
+/// Where [`AtomicFile::commit`] keeps the previous version of `path`:
+/// `CRC.xxhash3.bak`.
+pub fn backup_path(path: &Path) -> PathBuf {
+    with_suffix(path, BACKUP_SUFFIX)
+}
+
+/// Makes the rename durable: on Unix, a rename only survives a crash once
+/// the directory holding it is synced.
+#[cfg(unix)]
+fn sync_parent(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => File::open(dir)?.sync_all(),
+        _ => File::open(".")?.sync_all(),
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_parent(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+use std::fs::{self, File, Metadata};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+
+use crate::algo::HashAlgo;
+use crate::stamp::FileStamp;
+
+/// Conventional filename of the hash cache, kept in the output directory.
+pub const CACHE_FILE_NAME: &str = ".zhash-cache";
+
+/// First line of a cache file; files without it are ignored.
+const CACHE_HEADER: &str = "# zhash cache v1";
+
+/// Identifies a file's content without reading it: same device, inode, size
+/// and modification time as when it was hashed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    pub dev: u64,
+    pub ino: u64,
+    pub stamp: FileStamp,
+}
+
+impl CacheKey {
+    /// Key of the file described by `metadata`; `None` where inodes are not
+    /// available (non-Unix platforms), which disables caching.
+    #[cfg(unix)]
+    pub fn from_metadata(metadata: &Metadata) -> Option<Self> {
+        use std::os::unix::fs::MetadataExt;
+        let stamp = FileStamp::from_metadata(metadata).ok()?;
+        Some(Self { dev: metadata.dev(), ino: metadata.ino(), stamp })
+    }
+
+    #[cfg(not(unix))]
+    pub fn from_metadata(_metadata: &Metadata) -> Option<Self> {
+        None
+    }
+}
+
+/// Digests of previously hashed files, by [`CacheKey`] and algorithm.
+///
+/// Stored as one `dev ino size mtime_ns algo digest` line per digest, after
+/// a version header.
+#[derive(Debug, Default)]
+pub struct HashCache {
+    digests: HashMap<(CacheKey, HashAlgo), String>,
+}
+
+impl HashCache {
+    /// Reads the cache at `path`; a missing or unrecognized file yields an
+    /// empty cache. Malformed lines are skipped.
+    pub fn load(path: &Path) -> io::Result<HashCache> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if matches!(e.kind(), io::ErrorKind::NotFound | io::ErrorKind::InvalidData) => {
+                return Ok(HashCache::default());
+            }
+            Err(e) => return Err(e),
+        };
+        let mut lines = content.lines();
+        if lines.next() != Some(CACHE_HEADER) {
+            return Ok(HashCache::default());
+        }
+        let digests = lines
+            .filter_map(|line| {
+                let mut fields = line.split(' ');
+                let key = CacheKey {
+                    dev: fields.next()?.parse().ok()?,
+                    ino: fields.next()?.parse().ok()?,
+                    stamp: FileStamp { size: fields.next()?.parse().ok()?, mtime_ns: fields.next()?.parse().ok()? },
+                };
+                let algo = HashAlgo::from_name(fields.next()?)?;
+                Some(((key, algo), fields.next()?.to_string()))
+            })
+            .collect();
+        Ok(HashCache { digests })
+    }
+
+    pub fn get(&self, key: &CacheKey, algo: HashAlgo) -> Option<&str> {
+        self.digests.get(&(*key, algo)).map(String::as_str)
+    }
+
+    /// Cached digests for every one of `algos`, or `None` if any is missing.
+    pub fn get_all(&self, key: &CacheKey, algos: &[HashAlgo]) -> Option<Vec<(HashAlgo, String)>> {
+        algos.iter().map(|algo| Some((*algo, self.get(key, *algo)?.to_string()))).collect()
+    }
+
+    pub fn insert(&mut self, key: CacheKey, algo: HashAlgo, digest: String) {
+        self.digests.insert((key, algo), digest);
+    }
+
+    /// Drops every digest of `algos`, e.g. before recording a fresh run so
+    /// that files deleted since do not linger.
+    pub fn forget_algos(&mut self, algos: &[HashAlgo]) {
+        self.digests.retain(|(_, algo), _| !algos.contains(algo));
+    }
+
+    pub fn write_to(&self, out: &mut impl Write) -> io::Result<()> {
+        HashCache::write_header(out)?;
+        for ((key, algo), digest) in &self.digests {
+            HashCache::write_entry(out, key, *algo, digest)?;
+        }
+        Ok(())
+    }
+
+    /// First line of a cache file, for writers streaming its entries with
+    /// [`HashCache::write_entry`].
+    pub fn write_header(out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "{CACHE_HEADER}")
+    }
+
+    pub fn write_entry(out: &mut impl Write, key: &CacheKey, algo: HashAlgo, digest: &str) -> io::Result<()> {
+        writeln!(
+            out,
+            "{} {} {} {} {} {digest}",
+            key.dev, key.ino, key.stamp.size, key.stamp.mtime_ns, algo.name()
+        )
+    }
+
+    /// Copies to `out` the entries of the cache at `path` for algorithms
+    /// other than `algos`, without loading it: what is left of it once a
+    /// fresh run of `algos` is recorded, as with [`HashCache::forget_algos`].
+    pub fn copy_other_algos(path: &Path, algos: &[HashAlgo], out: &mut impl Write) -> io::Result<()> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        let mut lines = BufReader::new(file).lines();
+        if !matches!(lines.next(), Some(Ok(header)) if header == CACHE_HEADER) {
+            return Ok(());
+        }
+        for line in lines {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) if e.kind() == io::ErrorKind::InvalidData => return Ok(()),
+                Err(e) => return Err(e),
+            };
+            let algo = line.split(' ').nth(4).and_then(HashAlgo::from_name);
+            if algo.is_some_and(|algo| !algos.contains(&algo)) {
+                writeln!(out, "{line}")?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,169 @@
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::algo::HashAlgo;
+use crate::verify::{FileCheck, FileStatus, Verifier, VerifyObserver};
+
+const BAR_WIDTH: usize = 40;
+
+/// Draws one progress line per file as the verification advances.
+pub struct ConsoleObserver {
+    total_files: usize,
+    display_mutex: Mutex<()>,
+}
+
+impl ConsoleObserver {
+    pub fn new(total_files: usize) -> Self {
+        Self { total_files, display_mutex: Mutex::new(()) }
+    }
+
+    fn header(&self, index: usize, check: &FileCheck) -> String {
+        format!(
+            "\r\x1b[1;34m[{:>3}/{:<3}]\x1b[0m \x1b[36m{:<40}\x1b[0m",
+            index + 1, self.total_files, check.path
+        )
+    }
+}
+
+impl VerifyObserver for ConsoleObserver {
+    fn on_progress(&self, index: usize, check: &FileCheck, percent: f64) {
+        let filled = ((percent / 100.0) * BAR_WIDTH as f64).round() as usize;
+        let empty = BAR_WIDTH - filled;
+        let progress_bar = format!(
+            "{}{}",
+            "\x1b[44m \x1b[0m".repeat(filled),
+            "\x1b[100m \x1b[0m".repeat(empty)
+        );
+        print!(
+            "{} [{}] {:>6.1}%\x1b[K",
+            self.header(index, check), progress_bar, percent
+        );
+        io::stdout().flush().unwrap();
+    }
+
+    fn on_file_done(&self, index: usize, check: &FileCheck, status: &FileStatus) {
+        let _display_lock = self.display_mutex.lock().unwrap();
+        let progress_bar = "\x1b[44m \x1b[0m".repeat(BAR_WIDTH);
+        println!(
+            "{} [{}] 100.0%   {}{} {}\x1b[0m",
+            self.header(index, check),
+            progress_bar,
+            status.color(),
+            status.symbol(),
+            status.text()
+        );
+        io::stdout().flush().unwrap();
+    }
+}
+
+/// Title printed before the first manifest is loaded.
+pub fn print_title() {
+    println!("🔐 XXHash3 File Verifier - Command Line Version");
+    println!("{}", "=".repeat(60));
+    println!();
+}
+
+/// Describes the manifest at `path` just loaded into `verifier`.
+pub fn print_loaded(path: &Path, verifier: &Verifier) {
+    let algos: Vec<_> = verifier.algos.iter().map(|a| a.display_name()).collect();
+    println!("✓ Successfully loaded {} ({})", path.display(), algos.join(" + "));
+    println!("📂 Base directory: {}", verifier.base_path.display());
+    println!("📋 {} files to verify", verifier.files.len());
+}
+
+/// Explains that no manifest for `algos` was found by [`crate::Manifest::find`].
+pub fn print_not_found(error: &io::Error, algos: &[HashAlgo]) {
+    println!("\x1b[31m❌ Error: {}\x1b[0m", error);
+    let names: Vec<_> = algos.iter().map(|a| format!("'{}'", a.manifest_name())).collect();
+    println!("\nMake sure a {} file exists in:", names.join(" or "));
+    println!("  - Current directory");
+    println!("  - Executable directory");
+}
+
+/// Runs `verify` between the start and completion messages of a
+/// verification of `total_files` files, printed only when `console` is set,
+/// and returns how long it took.
+pub fn timed(total_files: usize, console: bool, verify: impl FnOnce()) -> Duration {
+    let start_time = Instant::now();
+    if console {
+        println!("\n\x1b[1m🔍 Starting verification of {} files...\x1b[0m", total_files);
+    }
+
+    verify();
+
+    let duration = start_time.elapsed();
+    if console {
+        println!();
+        println!("\nVerification completed in {:.2} seconds", duration.as_secs_f32());
+    }
+    duration
+}
+
+/// Prints the tallies of `verifier`'s last run, then every file needing attention.
+pub fn print_results(verifier: &Verifier) {
+    let summary = verifier.summary();
+
+    println!("\n{}", "=".repeat(60));
+    println!("📊 VERIFICATION RESULTS");
+    println!("{}", "=".repeat(60));
+
+    if !summary.has_problems() {
+        println!("\x1b[32m✅ VERIFICATION SUCCESSFUL!\x1b[0m");
+        println!("\x1b[32mAll files are intact.\x1b[0m");
+    } else {
+        println!("\x1b[33m⚠️ PROBLEMS DETECTED\x1b[0m");
+        println!("\x1b[33mSome files need your attention.\x1b[0m");
+    }
+
+    println!("\n📈 Detailed Statistics:");
+    println!(" \x1b[32m✓ OK files         : {:>4}\x1b[0m", summary.ok);
+    if summary.corrupted > 0 {
+        println!(" \x1b[31m✗ Corrupted files  : {:>4}\x1b[0m", summary.corrupted);
+    }
+    if summary.missing > 0 {
+        println!(" \x1b[33m? Missing files    : {:>4}\x1b[0m", summary.missing);
+    }
+    if summary.errors > 0 {
+        println!(" \x1b[31m! Read errors      : {:>4}\x1b[0m", summary.errors);
+    }
+    if summary.untracked > 0 {
+        println!(" \x1b[36m+ Untracked files  : {:>4}\x1b[0m", summary.untracked);
+    }
+    println!(" 📁 Total files      : {:>4}", summary.total);
+
+    if summary.has_problems() {
+        println!("\n⚠️ Problematic files:");
+        for file_check in &verifier.files {
+            match file_check.status {
+                Some(FileStatus::Corrupted) => println!(" \x1b[31m✗ CORRUPTED\x1b[0m : {}", file_check.path),
+                Some(FileStatus::Missing) => println!(" \x1b[33m? MISSING\x1b[0m   : {}", file_check.path),
+                Some(FileStatus::Error) => println!(" \x1b[31m! ERROR\x1b[0m     : {}", file_check.path),
+                Some(FileStatus::Untracked) => println!(" \x1b[36m+ UNTRACKED\x1b[0m : {}", file_check.path),
+                _ => {}
+            }
+        }
+    }
+
+    println!("\n{}", "=".repeat(60));
+
+    if summary.total > 0 {
+        let success_rate = (summary.ok as f32 / summary.total as f32) * 100.0;
+        if success_rate == 100.0 {
+            println!("\x1b[32m🎉 Success rate : {:.1}% - PERFECT!\x1b[0m", success_rate);
+        } else if success_rate >= 90.0 {
+            println!("\x1b[33m📊 Success rate : {:.1}% - Good\x1b[0m", success_rate);
+        } else {
+            println!("\x1b[31m📊 Success rate : {:.1}% - Attention required\x1b[0m", success_rate);
+        }
+    }
+}
+
+/// Keeps the console window open until Enter is pressed, for users who
+/// started the verifier by double-clicking it.
+pub fn wait_for_enter() {
+    println!("\nPress Enter to exit...");
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).unwrap();
+}
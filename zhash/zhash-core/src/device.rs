@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+use std::fs::Metadata;
+use std::io;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, Condvar, Mutex};
+
+/// Concurrent readers allowed on a device detected as slow to seek: a
+/// spinning disk, or any drive behind USB.
+pub const SLOW_DEVICE_THREADS: usize = 1;
+
+/// One `--io-threads` setting: `N` for every device, or `PATH=N` for the
+/// device holding `PATH`. `0` lifts the limit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IoThreads {
+    pub path: Option<PathBuf>,
+    pub threads: usize,
+}
+
+impl FromStr for IoThreads {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, String> {
+        let (path, threads) = match spec.rsplit_once('=') {
+            Some((path, threads)) => (Some(PathBuf::from(path)), threads),
+            None => (None, spec),
+        };
+        let threads = threads
+            .parse()
+            .map_err(|_| format!("expected N or PATH=N, with N a number of threads: {spec}"))?;
+        Ok(Self { path, threads })
+    }
+}
+
+/// Limits how many files are read at once from each device, independently
+/// of how many threads hash them, so that spinning disks are not made to
+/// seek between dozens of files.
+///
+/// A device's limit is, in order: the one given for it, the one given for
+/// every device, [`SLOW_DEVICE_THREADS`] if it is detected as slow (on
+/// Linux, from sysfs), or none. Where devices cannot be told apart (outside
+/// Unix), all files share one limit.
+#[derive(Debug, Default)]
+pub struct DeviceLimits {
+    default: Option<usize>,
+    explicit: HashMap<u64, usize>,
+    /// Semaphore of every device met so far; `None` when unlimited.
+    devices: Mutex<HashMap<u64, Option<Arc<Semaphore>>>>,
+}
+
+impl DeviceLimits {
+    /// Limits from `--io-threads` settings; fails if a `PATH` cannot be read.
+    pub fn new(settings: &[IoThreads]) -> io::Result<Self> {
+        let mut limits = Self::default();
+        for setting in settings {
+            match &setting.path {
+                Some(path) => {
+                    let metadata = path
+                        .metadata()
+                        .map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", path.display())))?;
+                    if let Some(dev) = device_id(&metadata) {
+                        limits.explicit.insert(dev, setting.threads);
+                    }
+                }
+                None => limits.default = Some(setting.threads),
+            }
+        }
+        Ok(limits)
+    }
+
+    /// Waits until the device holding the file described by `metadata` may
+    /// be read by one more thread; reading is allowed until the permit is
+    /// dropped. `None` when the device is unlimited.
+    pub fn acquire(&self, metadata: &Metadata) -> Option<DevicePermit> {
+        let dev = device_id(metadata)?;
+        let semaphore = self
+            .devices
+            .lock()
+            .unwrap()
+            .entry(dev)
+            .or_insert_with(|| self.limit_for(dev).map(|limit| Arc::new(Semaphore::new(limit))))
+            .clone()?;
+        semaphore.acquire();
+        Some(DevicePermit(semaphore))
+    }
+
+    fn limit_for(&self, dev: u64) -> Option<usize> {
+        let limit = match self.explicit.get(&dev).copied().or(self.default) {
+            Some(limit) => limit,
+            None => is_slow_device(dev).then_some(SLOW_DEVICE_THREADS)?,
+        };
+        (limit > 0).then_some(limit)
+    }
+}
+
+/// Right to read from a device, given back when dropped.
+pub struct DevicePermit(Arc<Semaphore>);
+
+impl Drop for DevicePermit {
+    fn drop(&mut self) {
+        self.0.release();
+    }
+}
+
+#[derive(Debug)]
+struct Semaphore {
+    available: Mutex<usize>,
+    released: Condvar,
+}
+
+impl Semaphore {
+    fn new(permits: usize) -> Self {
+        Self { available: Mutex::new(permits), released: Condvar::new() }
+    }
+
+    fn acquire(&self) {
+        let mut available = self.available.lock().unwrap();
+        while *available == 0 {
+            available = self.released.wait(available).unwrap();
+        }
+        *available -= 1;
+    }
+
+    fn release(&self) {
+        *self.available.lock().unwrap() += 1;
+        self.released.notify_one();
+    }
+}
+
+#[cfg(unix)]
+fn device_id(metadata: &Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.dev())
+}
+
+/// Without device numbers every file counts as being on one shared device,
+/// so that at least a global `--io-threads N` applies; `PATH=N` then limits
+/// every device as well.
+#[cfg(not(unix))]
+fn device_id(_metadata: &Metadata) -> Option<u64> {
+    Some(0)
+}
+
+/// Whether the block device `dev` is rotational or attached through USB,
+/// according to sysfs. Filesystems without a block device (network shares,
+/// tmpfs) are not.
+#[cfg(target_os = "linux")]
+fn is_slow_device(dev: u64) -> bool {
+    use std::fs;
+    use std::path::Path;
+
+    // glibc's encoding of dev_t, as decoded by major(3) and minor(3)
+    let major = ((dev >> 32) & 0xffff_f000) | ((dev >> 8) & 0x0000_0fff);
+    let minor = ((dev >> 12) & 0xffff_ff00) | (dev & 0x0000_00ff);
+    let Ok(dir) = fs::canonicalize(format!("/sys/dev/block/{major}:{minor}")) else {
+        return false;
+    };
+    if dir.to_string_lossy().contains("/usb") {
+        return true;
+    }
+    // Partitions have no queue of their own: it is their disk's
+    let rotational = |dir: &Path| fs::read_to_string(dir.join("queue/rotational")).ok();
+    let flag = rotational(&dir).or_else(|| rotational(dir.parent()?));
+    flag.is_some_and(|flag| flag.trim() == "1")
+}
+
+#[cfg(not(target_os = "linux"))]
+fn is_slow_device(_dev: u64) -> bool {
+    false
+}
@@ -0,0 +1,256 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::manifest::SortOrder;
+
+/// Records kept in memory before being spilled to a sorted run on disk.
+pub const DEFAULT_RUN_LEN: usize = 100_000;
+
+/// Runs merged at once, each holding an open file: more runs than this are
+/// merged in several passes, which keeps clear of the open-file limit.
+pub const MAX_FAN_IN: usize = 64;
+
+/// Sorts records of text fields by their first field with bounded memory.
+///
+/// Records are buffered up to a limit, then sorted and spilled as a run to a
+/// temporary file; [`ExternalSorter::finish`] merges the runs, at most
+/// [`MAX_FAN_IN`] at a time. Inputs that fit in one buffer never touch the
+/// disk.
+pub struct ExternalSorter {
+    order: SortOrder,
+    run_len: usize,
+    dir: PathBuf,
+    buffer: Vec<Vec<String>>,
+    runs: Vec<PathBuf>,
+    /// Number of the next run file, unique even once merged runs are deleted.
+    next_run: usize,
+}
+
+impl ExternalSorter {
+    /// Sorter spilling at most `run_len` records per run into `dir`, a
+    /// directory of its own, created now and removed with its contents once
+    /// the sorter is dropped.
+    pub fn new(dir: &Path, order: SortOrder, run_len: usize) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        Ok(Self {
+            order,
+            run_len: run_len.max(1),
+            dir: dir.to_path_buf(),
+            buffer: Vec::new(),
+            runs: Vec::new(),
+            next_run: 0,
+        })
+    }
+
+    /// Adds a record; its first field is the sort key.
+    pub fn push(&mut self, record: Vec<String>) -> io::Result<()> {
+        self.buffer.push(record);
+        if self.buffer.len() >= self.run_len {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    fn spill(&mut self) -> io::Result<()> {
+        self.sort_buffer();
+        let path = self.new_run_path();
+        let mut out = BufWriter::new(File::create(&path)?);
+        for record in self.buffer.drain(..) {
+            write_record(&mut out, &record)?;
+        }
+        out.flush()?;
+        self.runs.push(path);
+        Ok(())
+    }
+
+    fn new_run_path(&mut self) -> PathBuf {
+        self.next_run += 1;
+        self.dir.join(format!("run-{}.tmp", self.next_run - 1))
+    }
+
+    fn sort_buffer(&mut self) {
+        let order = self.order;
+        self.buffer.sort_by(|a, b| order.compare(&a[0], &b[0]));
+    }
+
+    /// Calls `emit` on every record in order, then deletes the runs and
+    /// their directory.
+    pub fn finish(mut self, emit: impl FnMut(Vec<String>) -> io::Result<()>) -> io::Result<()> {
+        if self.runs.is_empty() {
+            self.sort_buffer();
+            return self.buffer.drain(..).try_for_each(emit);
+        }
+        if !self.buffer.is_empty() {
+            self.spill()?;
+        }
+
+        // Each pass merges consecutive runs, so equal keys keep their order
+        while self.runs.len() > MAX_FAN_IN {
+            let runs = std::mem::take(&mut self.runs);
+            for group in runs.chunks(MAX_FAN_IN) {
+                if let [run] = group {
+                    self.runs.push(run.clone());
+                    continue;
+                }
+                let path = self.new_run_path();
+                let mut out = BufWriter::new(File::create(&path)?);
+                self.runs.push(path);
+                merge(group, self.order, |record| write_record(&mut out, &record))?;
+                out.flush()?;
+                for run in group {
+                    fs::remove_file(run)?;
+                }
+            }
+        }
+        merge(&self.runs, self.order, emit)
+    }
+}
+
+/// Calls `emit` on the records of the sorted `runs` in order.
+fn merge(runs: &[PathBuf], order: SortOrder, mut emit: impl FnMut(Vec<String>) -> io::Result<()>) -> io::Result<()> {
+    let mut readers = Vec::with_capacity(runs.len());
+    for path in runs {
+        readers.push(BufReader::new(File::open(path)?));
+    }
+    let mut heap = BinaryHeap::with_capacity(readers.len());
+    for (run, reader) in readers.iter_mut().enumerate() {
+        if let Some(record) = read_record(reader)? {
+            heap.push(Head { record, run, order });
+        }
+    }
+    while let Some(Head { record, run, .. }) = heap.pop() {
+        emit(record)?;
+        if let Some(record) = read_record(&mut readers[run])? {
+            heap.push(Head { record, run, order });
+        }
+    }
+    Ok(())
+}
+
+impl Drop for ExternalSorter {
+    fn drop(&mut self) {
+        // Whole, so that runs being merged when a pass failed go too
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Next record of one run, ordered so that [`BinaryHeap`] pops the smallest
+/// key first; equal keys come out in run order, which keeps the merge stable.
+struct Head {
+    record: Vec<String>,
+    run: usize,
+    order: SortOrder,
+}
+
+impl Ord for Head {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.order
+            .compare(&other.record[0], &self.record[0])
+            .then_with(|| other.run.cmp(&self.run))
+    }
+}
+
+impl PartialOrd for Head {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Head {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Head {}
+
+/// One record per line, fields separated by tabs, with `\`, tab, CR and LF
+/// escaped.
+pub(crate) fn write_record(out: &mut impl Write, record: &[String]) -> io::Result<()> {
+    let mut line = String::new();
+    for (i, field) in record.iter().enumerate() {
+        if i > 0 {
+            line.push('\t');
+        }
+        for c in field.chars() {
+            match c {
+                '\\' => line.push_str("\\\\"),
+                '\t' => line.push_str("\\t"),
+                '\n' => line.push_str("\\n"),
+                '\r' => line.push_str("\\r"),
+                _ => line.push(c),
+            }
+        }
+    }
+    line.push('\n');
+    out.write_all(line.as_bytes())
+}
+
+pub(crate) fn read_record(reader: &mut impl BufRead) -> io::Result<Option<Vec<String>>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(decode_record(line.strip_suffix('\n').unwrap_or(&line))))
+}
+
+/// Splits and unescapes one line written by [`write_record`], without its newline.
+pub(crate) fn decode_record(line: &str) -> Vec<String> {
+    line.split('\t')
+        .map(|field| {
+            let mut unescaped = String::with_capacity(field.len());
+            let mut chars = field.chars();
+            while let Some(c) = chars.next() {
+                if c != '\\' {
+                    unescaped.push(c);
+                    continue;
+                }
+                match chars.next() {
+                    Some('t') => unescaped.push('\t'),
+                    Some('n') => unescaped.push('\n'),
+                    Some('r') => unescaped.push('\r'),
+                    Some(other) => unescaped.push(other),
+                    None => {}
+                }
+            }
+            unescaped
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 386 records in runs of 3 make 129 runs: two full groups of
+    /// [`MAX_FAN_IN`] plus a lone run carried over to the last pass.
+    #[test]
+    fn multi_pass_merge_is_ordered_and_stable() {
+        let dir = std::env::temp_dir().join(format!("zhash-extsort-{}", std::process::id()));
+        let records: Vec<Vec<String>> = (0..386)
+            .map(|i| vec![format!("key{}", (i * 7919) % 23), i.to_string(), "tab\there\nand newline".to_string()])
+            .collect();
+        let mut sorter = ExternalSorter::new(&dir, SortOrder::Natural, 3).unwrap();
+        for record in &records {
+            sorter.push(record.clone()).unwrap();
+        }
+        // The last two records make the 129th run once finishing
+        assert_eq!(sorter.runs.len(), 2 * MAX_FAN_IN);
+
+        let mut sorted = Vec::new();
+        sorter
+            .finish(|record| {
+                sorted.push(record);
+                Ok(())
+            })
+            .unwrap();
+
+        let mut expected = records;
+        expected.sort_by(|a, b| SortOrder::Natural.compare(&a[0], &b[0]));
+        assert_eq!(sorted, expected);
+        assert!(!dir.exists());
+    }
+}
@@ -0,0 +1,259 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use crate::algo::{HashAlgo, Hasher};
+use crate::tree::{tree_hash_bytes, tree_hash_file};
+
+/// Files up to this size are read into memory and hashed in one shot.
+///
+/// Per `examples/io_bench.rs`, loading a file whole is the fastest way up to
+/// a few tens of MiB, and gets about three times slower than streaming it
+/// beyond that.
+pub const DEFAULT_FULL_LOAD_LIMIT: u64 = 16 * 1024 * 1024;
+
+/// Size of the read buffer used when streaming large files.
+pub const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// How file contents are brought into memory to be hashed.
+///
+/// `examples/io_bench.rs` times each of them on files of several sizes.
+/// Memory maps come out about 10% ahead of buffered reads on large cached
+/// files, not enough for [`IoStrategy::Auto`] to take the risk described
+/// under [`IoStrategy::Mmap`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum IoStrategy {
+    /// `whole` up to the full-load limit, `read` above it.
+    #[default]
+    Auto,
+    /// Stream the file through a [`CHUNK_SIZE`] buffer.
+    Read,
+    /// Map the file in memory if it sits on a read-only mount, stream it
+    /// like [`IoStrategy::Read`] otherwise. A mapped file truncated by
+    /// another process while it is hashed kills this one with `SIGBUS`;
+    /// [`IoOptions::mmap_unchecked`] maps files on writable mounts as well.
+    Mmap,
+    /// Read the whole file into memory first, whatever its size.
+    Whole,
+}
+
+/// I/O settings shared by the hashing functions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IoOptions {
+    pub strategy: IoStrategy,
+    /// Largest file [`IoStrategy::Auto`] loads whole.
+    pub full_load_limit: u64,
+    /// Read and hash the leaves of a tree hash in parallel, on the rayon
+    /// pool; see [`IoOptions::holding_permit`].
+    pub parallel_tree: bool,
+    /// Map files under [`IoStrategy::Mmap`] whatever their mount.
+    pub mmap_unchecked: Option<MmapUnchecked>,
+}
+
+/// Promise that no file hashed under [`IoStrategy::Mmap`] is truncated
+/// while it is mapped, letting [`IoOptions::mmap_unchecked`] map files on
+/// writable mounts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MmapUnchecked(());
+
+impl MmapUnchecked {
+    /// # Safety
+    ///
+    /// Nothing may truncate the files hashed with these options while they
+    /// are hashed: reading a mapped page past the new end of a file raises
+    /// `SIGBUS`, and the data of the map may change under the hashers.
+    pub unsafe fn new() -> Self {
+        Self(())
+    }
+}
+
+impl Default for IoOptions {
+    fn default() -> Self {
+        Self { strategy: IoStrategy::Auto, full_load_limit: DEFAULT_FULL_LOAD_LIMIT, parallel_tree: true, mmap_unchecked: None }
+    }
+}
+
+impl IoOptions {
+    /// Strategy used for a file of `size` bytes: [`IoStrategy::Auto`] resolved.
+    pub fn strategy_for(&self, size: u64) -> IoStrategy {
+        match self.strategy {
+            IoStrategy::Auto if size <= self.full_load_limit => IoStrategy::Whole,
+            IoStrategy::Auto => IoStrategy::Read,
+            strategy => strategy,
+        }
+    }
+
+    /// Options for hashing while a [`DevicePermit`](crate::device::DevicePermit)
+    /// is held (`held`): tree leaves are then hashed one after the other.
+    ///
+    /// A rayon worker waiting on nested parallel work may run another
+    /// file's job meanwhile, which would block on the same device and never
+    /// let the first one finish; parallel reads would also defeat the
+    /// device's limit.
+    pub fn holding_permit(self, held: bool) -> Self {
+        Self { parallel_tree: self.parallel_tree && !held, ..self }
+    }
+}
+
+/// Hashes `path` with the strategy `io` picks for it, and returns its hex
+/// digest together with its size.
+pub fn hash_file(path: &Path, algo: HashAlgo, io: IoOptions) -> io::Result<(String, u64)> {
+    let (mut digests, size) = hash_file_multi(path, &[algo], io)?;
+    Ok((digests.remove(0), size))
+}
+
+/// Like [`hash_file`], computing one digest per entry of `algos` in a single read.
+pub fn hash_file_multi(path: &Path, algos: &[HashAlgo], io: IoOptions) -> io::Result<(Vec<String>, u64)> {
+    hash_path(path, algos, io, |_, _| {})
+}
+
+/// Like [`hash_file`], calling `progress(hashed, total)` after every chunk.
+pub fn hash_file_with_progress(
+    path: &Path,
+    algo: HashAlgo,
+    io: IoOptions,
+    progress: impl FnMut(u64, u64) + Send,
+) -> io::Result<String> {
+    let mut digests = hash_file_multi_with_progress(path, &[algo], io, progress)?;
+    Ok(digests.remove(0))
+}
+
+/// Like [`hash_file_with_progress`], computing one digest per entry of `algos`.
+///
+/// A tree hash alone has its leaves hashed in parallel; read from disk, they
+/// are also read in parallel, with positional reads, even under
+/// [`IoStrategy::Mmap`].
+pub fn hash_file_multi_with_progress(
+    path: &Path,
+    algos: &[HashAlgo],
+    io: IoOptions,
+    progress: impl FnMut(u64, u64) + Send,
+) -> io::Result<Vec<String>> {
+    Ok(hash_path(path, algos, io, progress)?.0)
+}
+
+fn hash_path(
+    path: &Path,
+    algos: &[HashAlgo],
+    io: IoOptions,
+    mut progress: impl FnMut(u64, u64) + Send,
+) -> io::Result<(Vec<String>, u64)> {
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len();
+    let tree_only = matches!(algos, [algo] if algo.is_tree());
+    let digests = match io.strategy_for(size) {
+        IoStrategy::Whole => {
+            let mut buf = Vec::with_capacity(size as usize);
+            file.read_to_end(&mut buf)?;
+            hash_in_memory(&buf, algos, io.parallel_tree, &mut progress)
+        }
+        _ if tree_only => vec![tree_hash_file(&file, size, io.parallel_tree, progress)?],
+        IoStrategy::Mmap if io.mmap_unchecked.is_some() || is_read_only_mount(&file) => {
+            hash_mapped(&file, size, algos, io.parallel_tree, &mut progress)?
+        }
+        _ => hash_reader(&mut file, algos, size, progress)?,
+    };
+    Ok((digests, size))
+}
+
+/// Hashes a buffer in [`CHUNK_SIZE`] steps, reporting progress after each;
+/// a lone tree hash in one parallel step, with `parallel_tree`.
+fn hash_in_memory(
+    data: &[u8],
+    algos: &[HashAlgo],
+    parallel_tree: bool,
+    progress: &mut impl FnMut(u64, u64),
+) -> Vec<String> {
+    let total = data.len() as u64;
+    if parallel_tree
+        && let [algo] = algos
+        && algo.is_tree()
+    {
+        let digest = tree_hash_bytes(data);
+        progress(total, total);
+        return vec![digest];
+    }
+    let mut hashers: Vec<Hasher> = algos.iter().map(|algo| Hasher::new(*algo)).collect();
+    let mut hashed = 0u64;
+    for chunk in data.chunks(CHUNK_SIZE) {
+        for hasher in &mut hashers {
+            hasher.update(chunk);
+        }
+        hashed += chunk.len() as u64;
+        progress(hashed, total);
+    }
+    hashers.into_iter().map(Hasher::finalize).collect()
+}
+
+/// Whether `file` sits on a filesystem mounted read-only, which nothing can
+/// truncate it through.
+#[cfg(target_os = "linux")]
+fn is_read_only_mount(file: &File) -> bool {
+    use std::os::fd::AsRawFd;
+
+    let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `stat` is only read once `fstatvfs` has filled it in
+    if unsafe { libc::fstatvfs(file.as_raw_fd(), stat.as_mut_ptr()) } != 0 {
+        return false;
+    }
+    let stat = unsafe { stat.assume_init() };
+    stat.f_flag & libc::ST_RDONLY != 0
+}
+
+#[cfg(not(target_os = "linux"))]
+fn is_read_only_mount(_file: &File) -> bool {
+    false
+}
+
+/// Hashes the first `size` bytes of `file` through a memory map; see
+/// [`IoStrategy::Mmap`] for why it must not be truncated meanwhile.
+fn hash_mapped(
+    file: &File,
+    size: u64,
+    algos: &[HashAlgo],
+    parallel_tree: bool,
+    progress: &mut impl FnMut(u64, u64),
+) -> io::Result<Vec<String>> {
+    // Mapping an empty file fails on some systems
+    if size == 0 {
+        return Ok(hash_in_memory(&[], algos, parallel_tree, progress));
+    }
+    // SAFETY: the map is only read, and the file is either on a read-only
+    // mount or covered by the promise of `MmapUnchecked::new`.
+    let map = unsafe { Mmap::map(file)? };
+    #[cfg(unix)]
+    let _ = map.advise(memmap2::Advice::Sequential);
+    let data = &map[..size.min(map.len() as u64) as usize];
+    Ok(hash_in_memory(data, algos, parallel_tree, progress))
+}
+
+/// Streams any reader through every algorithm of `algos` at once; `total_size`
+/// is only used for progress reporting.
+pub fn hash_reader(
+    reader: &mut impl Read,
+    algos: &[HashAlgo],
+    total_size: u64,
+    mut progress: impl FnMut(u64, u64),
+) -> io::Result<Vec<String>> {
+    let mut hashers: Vec<Hasher> = algos.iter().map(|algo| Hasher::new(*algo)).collect();
+    // Small files need no full-size buffer, whose zeroing would dominate their hashing
+    let mut buffer = vec![0u8; (total_size as usize).saturating_add(1).clamp(64 * 1024, CHUNK_SIZE)];
+    let mut read_bytes = 0u64;
+
+    loop {
+        let n = reader.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        for hasher in &mut hashers {
+            hasher.update(&buffer[..n]);
+        }
+        read_bytes += n as u64;
+        progress(read_bytes, total_size);
+    }
+
+    Ok(hashers.into_iter().map(Hasher::finalize).collect())
+}
@@ -0,0 +1,73 @@
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::extsort::{decode_record, write_record};
+
+/// Append-only log of the work a long run has completed, so that an
+/// interrupted run can resume instead of starting over.
+///
+/// A header line describes the run; records of a journal whose header
+/// differs, e.g. written with other algorithms, are not reused. Each record
+/// is flushed as soon as it is written, so a killed process loses at most
+/// the record being written.
+pub struct Journal {
+    path: PathBuf,
+    out: BufWriter<File>,
+}
+
+impl Journal {
+    /// Opens the journal at `path` for a run described by `header` (a single
+    /// line). With `resume`, the records of a previous run with the same
+    /// header are returned and kept; otherwise the journal starts empty.
+    pub fn open(path: &Path, header: &str, resume: bool) -> io::Result<(Journal, Vec<Vec<String>>)> {
+        let records = if resume { read_journal(path, header)? } else { Vec::new() };
+        // Rewritten rather than appended to, which drops a record cut short by a crash
+        let mut out = BufWriter::new(File::create(path)?);
+        writeln!(out, "{header}")?;
+        for record in &records {
+            write_record(&mut out, record)?;
+        }
+        out.flush()?;
+        Ok((Journal { path: path.to_path_buf(), out }, records))
+    }
+
+    pub fn record(&mut self, record: &[String]) -> io::Result<()> {
+        write_record(&mut self.out, record)?;
+        self.out.flush()
+    }
+
+    /// Deletes the journal once the run it describes is complete.
+    pub fn remove(self) -> io::Result<()> {
+        let Journal { path, out } = self;
+        drop(out);
+        fs::remove_file(path)
+    }
+}
+
+/// Complete records of the journal at `path` if it was written under
+/// `header`; none if it is missing or belongs to another kind of run.
+fn read_journal(path: &Path, header: &str) -> io::Result<Vec<Vec<String>>> {
+    let mut reader = match File::open(path) {
+        Ok(file) => BufReader::new(file),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut first = String::new();
+    reader.read_line(&mut first)?;
+    if first.trim_end_matches(['\r', '\n']) != header {
+        return Ok(Vec::new());
+    }
+    let mut records = Vec::new();
+    let mut line = Vec::new();
+    while reader.read_until(b'\n', &mut line)? > 0 {
+        // A line without its newline was being written when the run stopped
+        if let Some(complete) = line.strip_suffix(b"\n")
+            && let Ok(complete) = std::str::from_utf8(complete)
+        {
+            records.push(decode_record(complete));
+        }
+        line.clear();
+    }
+    Ok(records)
+}
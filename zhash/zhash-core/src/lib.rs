@@ -0,0 +1,45 @@
+//! Shared engine behind `zhashgen`, `zhsh` and `xxh3`.
+//!
+//! * [`algo`] — supported hash algorithms and streaming hashers.
+//! * [`atomic`] — crash-safe file replacement.
+//! * [`hash`] — hashing files from disk.
+//! * [`journal`] — checkpoints letting interrupted runs resume.
+//! * [`device`] — per-device limits on concurrent reads.
+//! * [`console`] — terminal output of the verifiers (`console` feature).
+//! * [`cache`] — on-disk cache of digests keyed by inode, size and mtime.
+//! * [`extsort`] — sorting more manifest lines than fit in memory.
+//! * [`manifest`] — reading and writing `CRC.*` manifests (GNU coreutils, BSD-tagged and SFV formats).
+//! * [`verify`] — checking a manifest against the filesystem.
+//! * [`order`] — reading files in their on-disk order.
+//! * [`report`] — JSON reports of verification runs.
+//! * [`sidecar`] — names of the files kept next to a manifest.
+//! * [`throttle`] — keeping background runs from hogging disks and CPUs.
+//! * [`tree`] — tree hashing, parallel within a single file.
+//! * [`stamp`] — sizes and modification times recorded next to a manifest,
+//!   for incremental updates.
+//! * [`walk`] — collecting the files of a tree.
+
+pub mod algo;
+pub mod atomic;
+pub mod cache;
+#[cfg(feature = "console")]
+pub mod console;
+pub mod device;
+pub mod extsort;
+pub mod hash;
+pub mod journal;
+pub mod manifest;
+pub mod order;
+pub mod report;
+pub mod sidecar;
+pub mod stamp;
+pub mod throttle;
+pub mod tree;
+pub mod verify;
+pub mod walk;
+
+pub use algo::{HashAlgo, Hasher};
+pub use hash::{DEFAULT_FULL_LOAD_LIMIT, IoOptions, IoStrategy, MmapUnchecked, hash_file, hash_file_with_progress};
+pub use manifest::{LinkEntry, Manifest, ManifestEntry, ManifestFormat, SortOrder};
+pub use report::Report;
+pub use verify::{FileCheck, FileOutcome, FileStatus, Summary, Verifier, VerifyExit, VerifyObserver};
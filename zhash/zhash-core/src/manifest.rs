@@ -0,0 +1,699 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+use crate::algo::HashAlgo;
+use crate::walk::absolute_normalized;
+
+/// Conventional filename of manifests holding several digests per file.
+pub const MULTI_MANIFEST_NAME: &str = "CRC.multi";
+
+/// Conventional filename of SFV (Simple File Verification) manifests.
+pub const SFV_MANIFEST_NAME: &str = "CRC.sfv";
+
+/// Header announcing the digest columns of a multi-algorithm manifest,
+/// e.g. `# algorithms: xxh3 sha256`.
+const ALGORITHMS_HEADER: &str = "# algorithms:";
+
+/// Keyword of [`LinkEntry`] comment lines.
+const LINK_COMMENT: &str = "link:";
+
+/// Line layout of a manifest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum ManifestFormat {
+    /// GNU coreutils: `digest *path`, several digests per line in
+    /// multi-algorithm manifests.
+    #[default]
+    Gnu,
+    /// BSD-style tags: `XXH3 (path) = digest`, one line per digest, so a
+    /// single manifest may mix algorithms.
+    Tagged,
+    /// Simple File Verification: `path CRC32`, with `;` comment lines.
+    /// CRC32 only.
+    Sfv,
+}
+
+/// Order of the entries of a written manifest, so that manifests of identical
+/// trees are identical whatever order the filesystem lists files in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum SortOrder {
+    /// Byte-wise on the relative path, as `LC_ALL=C sort` does.
+    #[default]
+    Bytes,
+    /// Digit runs compared by value, so `file2` comes before `file10`; ties
+    /// are broken byte-wise.
+    Natural,
+}
+
+impl SortOrder {
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        match self {
+            SortOrder::Bytes => a.cmp(b),
+            SortOrder::Natural => natural_cmp(a, b).then_with(|| a.cmp(b)),
+        }
+    }
+}
+
+/// One file listed in a manifest, with one digest per algorithm.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestEntry {
+    /// Unescaped path, `/`-separated, relative to the manifest's base directory.
+    pub path: String,
+    pub digests: Vec<(HashAlgo, String)>,
+    /// GNU binary (`*`) rather than text (` `) mode marker.
+    pub binary: bool,
+}
+
+impl ManifestEntry {
+    /// Binary-mode entry for `path`, as returned by [`relative_name`].
+    pub fn new(path: String, digests: Vec<(HashAlgo, String)>) -> Self {
+        Self { path, digests, binary: true }
+    }
+
+    /// Stored digest for `algo`, if any.
+    pub fn digest(&self, algo: HashAlgo) -> Option<&str> {
+        self.digests.iter().find(|(a, _)| *a == algo).map(|(_, d)| d.as_str())
+    }
+
+    /// Formats the entry as a GNU coreutils line, `digest *path`, with one
+    /// space-separated digest per algorithm.
+    ///
+    /// As in coreutils, names containing a backslash, CR or LF are escaped and
+    /// the line is then prefixed with a backslash.
+    pub fn to_line(&self) -> String {
+        let digests: Vec<&str> = self.digests.iter().map(|(_, d)| d.as_str()).collect();
+        let marker = if self.binary { '*' } else { ' ' };
+        let escaped = escape_name(&self.path);
+        let prefix = if escaped.len() != self.path.len() { "\\" } else { "" };
+        format!("{prefix}{} {marker}{escaped}\n", digests.join(" "))
+    }
+
+    /// Formats the entry in `format`: one line for GNU and SFV, one line
+    /// per digest for tagged manifests.
+    ///
+    /// Fails only for SFV, on names it cannot represent.
+    pub fn format(&self, format: ManifestFormat) -> io::Result<String> {
+        match format {
+            ManifestFormat::Gnu => Ok(self.to_line()),
+            ManifestFormat::Tagged => Ok(self.to_tagged_lines()),
+            ManifestFormat::Sfv => self.to_sfv_line(),
+        }
+    }
+
+    /// Formats the entry as an SFV `path CRC32` line, with the customary
+    /// uppercase digest.
+    ///
+    /// SFV has no escaping, nor any digest but CRC32: names containing a
+    /// backslash, CR or LF are rejected rather than written as a line that
+    /// would read back as another path.
+    pub fn to_sfv_line(&self) -> io::Result<String> {
+        if self.path.contains(['\\', '\n', '\r']) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{:?}: SFV cannot represent names containing a backslash, CR or LF", self.path),
+            ));
+        }
+        let crc = self.digest(HashAlgo::Crc32).unwrap_or_default();
+        Ok(format!("{} {}\n", self.path, crc.to_uppercase()))
+    }
+
+    /// Parses one SFV `path CRC32` line; the path may contain spaces.
+    ///
+    /// Backslashes are read as path separators only when the manifest comes
+    /// from Windows (`windows`); elsewhere they are part of the name.
+    pub fn parse_sfv_line(line: &str, windows: bool) -> Option<ManifestEntry> {
+        let (name, crc) = line.trim_end().rsplit_once(' ')?;
+        let name = name.trim_end();
+        if name.is_empty() || crc.len() != 8 || !crc.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        Some(ManifestEntry {
+            path: if windows { name.replace('\\', "/") } else { name.to_string() },
+            digests: vec![(HashAlgo::Crc32, crc.to_lowercase())],
+            binary: true,
+        })
+    }
+
+    /// Formats the entry as BSD-style `TAG (path) = digest` lines, escaping
+    /// names like [`ManifestEntry::to_line`].
+    pub fn to_tagged_lines(&self) -> String {
+        let escaped = escape_name(&self.path);
+        let prefix = if escaped.len() != self.path.len() { "\\" } else { "" };
+        self.digests
+            .iter()
+            .map(|(algo, digest)| format!("{prefix}{} ({escaped}) = {digest}\n", algo.display_name()))
+            .collect()
+    }
+
+    /// Parses one BSD-style `TAG (path) = digest` line.
+    pub fn parse_tagged_line(line: &str) -> Option<ManifestEntry> {
+        let (escaped, rest) = match line.strip_prefix('\\') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let (tag, rest) = rest.split_once(" (")?;
+        let algo = HashAlgo::from_tag(tag)?;
+        let (name, digest) = rest.rsplit_once(") = ")?;
+        if name.is_empty() || digest.is_empty() || !digest.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        let path = if escaped { unescape_name(name)? } else { name.replace('\\', "/") };
+        let digest = digest.to_lowercase();
+
+        Some(ManifestEntry { path, digests: vec![(algo, digest)], binary: true })
+    }
+
+    /// Parses one GNU coreutils line holding a digest for each of `algos`.
+    ///
+    /// Lines without a mode marker (`digest path`) are accepted as text mode.
+    /// Lines whose digests are not hexadecimal are rejected.
+    /// Unescaped names are assumed to come from Windows tools if they contain
+    /// backslashes, which are then read as path separators.
+    pub fn parse_line(line: &str, algos: &[HashAlgo]) -> Option<ManifestEntry> {
+        let (escaped, mut rest) = match line.strip_prefix('\\') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+
+        let mut digests = Vec::with_capacity(algos.len());
+        for algo in algos {
+            let (hash, tail) = rest.split_once(' ')?;
+            if !is_hex_digest(hash) {
+                return None;
+            }
+            let digest = match algo {
+                HashAlgo::Crc32 => hash.to_lowercase(),
+                _ => hash.to_string(),
+            };
+            digests.push((*algo, digest));
+            rest = tail;
+        }
+
+        let (binary, name) = match rest.as_bytes().first()? {
+            b'*' => (true, &rest[1..]),
+            b' ' => (false, &rest[1..]),
+            _ => (false, rest),
+        };
+        if name.is_empty() {
+            return None;
+        }
+        let path = if escaped {
+            unescape_name(name)?
+        } else {
+            name.replace('\\', "/")
+        };
+
+        Some(ManifestEntry { path, digests, binary })
+    }
+}
+
+/// A symbolic link listed in a manifest with its target.
+///
+/// Written as a comment line, `# link: "path" -> "target"` (`;` in SFV
+/// manifests), so that tools unaware of it skip the line. Both names are
+/// JSON strings, which keeps any character unambiguous.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkEntry {
+    /// Same convention as [`ManifestEntry::path`].
+    pub path: String,
+    /// Target as stored in the link, unresolved.
+    pub target: String,
+}
+
+impl LinkEntry {
+    pub fn format(&self, format: ManifestFormat) -> String {
+        let comment = if format == ManifestFormat::Sfv { ';' } else { '#' };
+        let quote = |s: &str| serde_json::to_string(s).unwrap_or_default();
+        format!("{comment} {LINK_COMMENT} {} -> {}\n", quote(&self.path), quote(&self.target))
+    }
+
+    /// Parses a line written by [`LinkEntry::format`], comment marker included.
+    pub fn parse_line(line: &str) -> Option<LinkEntry> {
+        let rest = line.strip_prefix(['#', ';'])?.trim_start().strip_prefix(LINK_COMMENT)?.trim_start();
+        let mut strings = serde_json::Deserializer::from_str(rest).into_iter::<String>();
+        let path = strings.next()?.ok()?;
+        let rest = rest[strings.byte_offset()..].trim_start().strip_prefix("->")?;
+        let target = serde_json::from_str(rest.trim()).ok()?;
+        Some(LinkEntry { path, target })
+    }
+}
+
+/// Parsed contents of a manifest file.
+#[derive(Debug, Clone)]
+pub struct Manifest {
+    /// Digest columns, in line order; a single algorithm for classic manifests.
+    pub algos: Vec<HashAlgo>,
+    pub entries: Vec<ManifestEntry>,
+    pub links: Vec<LinkEntry>,
+}
+
+impl Manifest {
+    pub fn new(algos: Vec<HashAlgo>) -> Self {
+        Self { algos, entries: Vec::new(), links: Vec::new() }
+    }
+
+    /// Reads and parses the manifest at `path`.
+    ///
+    /// Tagged lines name their own algorithm, and multi-algorithm manifests
+    /// announce their columns in a header. For other manifests, when `algo`
+    /// is `None` the algorithm is taken from the extension, or failing that
+    /// from the length of the first digest.
+    ///
+    /// SFV manifests are recognized by their `.sfv` extension or a leading
+    /// `;` comment.
+    pub fn load(path: &Path, algo: Option<HashAlgo>) -> io::Result<Manifest> {
+        let buffer = fs::read(path)?;
+        let content = String::from_utf8_lossy(&buffer);
+        let is_sfv = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("sfv"))
+            || content.trim_start().starts_with(';');
+        if is_sfv {
+            return Ok(Manifest::parse_sfv(&content));
+        }
+        let algos = column_algos(path, &content, algo)?;
+        Ok(Manifest::parse(&content, algos))
+    }
+
+    /// Passes `visit` the entries of the manifest at `path` one line at a
+    /// time, without holding the file in memory, for manifests too large to
+    /// [`Manifest::load`].
+    ///
+    /// Formats and algorithms are recognized as by [`Manifest::load`], from
+    /// the lines up to the first entry. Tagged lines are passed as they
+    /// come, one digest each, and links are skipped.
+    pub fn read_entries(
+        path: &Path,
+        algo: Option<HashAlgo>,
+        mut visit: impl FnMut(ManifestEntry) -> io::Result<()>,
+    ) -> io::Result<()> {
+        let mut reader = BufReader::new(File::open(path)?);
+        // Header and comments, up to and including the first entry
+        let mut head = String::new();
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            if reader.read_until(b'\n', &mut line)? == 0 {
+                break;
+            }
+            let text = String::from_utf8_lossy(&line);
+            head.push_str(&text);
+            let text = text.trim();
+            if !text.is_empty() && !text.starts_with('#') && !text.starts_with(';') {
+                break;
+            }
+        }
+
+        let is_sfv = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("sfv"))
+            || head.trim_start().starts_with(';');
+        if is_sfv {
+            let windows = head.contains("\r\n");
+            return read_lines(head.as_bytes().chain(reader), |line| {
+                match ManifestEntry::parse_sfv_line(line.trim_start(), windows) {
+                    Some(entry) => visit(entry),
+                    None => Ok(()),
+                }
+            });
+        }
+        let algos = column_algos(path, &head, algo)?;
+        read_lines(head.as_bytes().chain(reader), |line| {
+            let line = line.trim_start().trim_end_matches('\r');
+            if line.is_empty() || line.starts_with('#') {
+                return Ok(());
+            }
+            let entry = match ManifestEntry::parse_tagged_line(line) {
+                Some(entry) => Some(entry),
+                None if algos.is_empty() => None,
+                None => ManifestEntry::parse_line(line, &algos),
+            };
+            entry.map_or(Ok(()), &mut visit)
+        })
+    }
+
+    /// Parses manifest text. GNU coreutils lines hold one digest per entry of
+    /// `algos`; BSD-style tagged lines may use any algorithm, and several
+    /// tagged lines for the same path are merged into one entry.
+    ///
+    /// Blank, comment (`#`) and malformed lines are skipped; an
+    /// `# algorithms:` header overrides `algos`.
+    pub fn parse(content: &str, algos: Vec<HashAlgo>) -> Manifest {
+        let mut manifest = Manifest::new(header_algos(content).unwrap_or(algos));
+        let mut tagged_index: HashMap<String, usize> = HashMap::new();
+
+        for line in content.lines() {
+            let line = line.trim_start().trim_end_matches('\r');
+            if line.is_empty() || line.starts_with('#') {
+                manifest.links.extend(LinkEntry::parse_line(line));
+                continue;
+            }
+            if let Some(entry) = ManifestEntry::parse_tagged_line(line) {
+                for (algo, _) in &entry.digests {
+                    if !manifest.algos.contains(algo) {
+                        manifest.algos.push(*algo);
+                    }
+                }
+                match tagged_index.get(&entry.path) {
+                    Some(&index) => manifest.entries[index].digests.extend(entry.digests),
+                    None => {
+                        tagged_index.insert(entry.path.clone(), manifest.entries.len());
+                        manifest.entries.push(entry);
+                    }
+                }
+                continue;
+            }
+            if manifest.algos.is_empty() {
+                continue;
+            }
+            if let Some(entry) = ManifestEntry::parse_line(line, &manifest.algos) {
+                manifest.entries.push(entry);
+            }
+        }
+
+        manifest
+    }
+
+    /// Parses SFV text; `;` comments, blank and malformed lines are skipped.
+    ///
+    /// CRLF line endings mark a manifest written on Windows, whose names use
+    /// `\` as separator; see [`ManifestEntry::parse_sfv_line`].
+    pub fn parse_sfv(content: &str) -> Manifest {
+        let mut manifest = Manifest::new(vec![HashAlgo::Crc32]);
+        let windows = content.contains("\r\n");
+        for line in content.lines().map(str::trim_start) {
+            if line.starts_with(';') {
+                manifest.links.extend(LinkEntry::parse_line(line));
+            } else if let Some(entry) = ManifestEntry::parse_sfv_line(line, windows) {
+                manifest.entries.push(entry);
+            }
+        }
+        manifest
+    }
+
+    /// Conventional filename for a manifest of `algos` in `format`:
+    /// [`SFV_MANIFEST_NAME`] for SFV, `CRC.<algo>` for a single algorithm,
+    /// [`MULTI_MANIFEST_NAME`] otherwise.
+    pub fn default_name(algos: &[HashAlgo], format: ManifestFormat) -> &'static str {
+        match (format, algos) {
+            (ManifestFormat::Sfv, _) => SFV_MANIFEST_NAME,
+            (_, [algo]) => algo.manifest_name(),
+            _ => MULTI_MANIFEST_NAME,
+        }
+    }
+
+    /// Looks for a conventionally named manifest (`CRC.xxhash3`, `CRC.md5`, ...)
+    /// for each of `algos` in each of `dirs`, in order.
+    ///
+    /// A [`MULTI_MANIFEST_NAME`] manifest is found too; its algorithm is
+    /// reported as `None` since it comes from the file's header. So is a
+    /// [`SFV_MANIFEST_NAME`] one when CRC32 is among `algos`.
+    pub fn find(dirs: &[PathBuf], algos: &[HashAlgo]) -> Option<(PathBuf, Option<HashAlgo>)> {
+        let sfv = algos
+            .contains(&HashAlgo::Crc32)
+            .then_some((SFV_MANIFEST_NAME, Some(HashAlgo::Crc32)));
+        dirs.iter().find_map(|dir| {
+            algos
+                .iter()
+                .map(|algo| (algo.manifest_name(), Some(*algo)))
+                .chain(sfv)
+                .chain([(MULTI_MANIFEST_NAME, None)])
+                .find_map(|(name, algo)| {
+                    let candidate = dir.join(name);
+                    candidate.exists().then_some((candidate, algo))
+                })
+        })
+    }
+
+    /// Directories searched by [`Manifest::find`] by default: the current
+    /// directory, then the directory holding the running executable.
+    pub fn default_search_dirs() -> Vec<PathBuf> {
+        let mut dirs = vec![std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."))];
+        if let Some(exe_dir) = std::env::current_exe().ok().and_then(|exe| exe.parent().map(Path::to_path_buf)) {
+            dirs.push(exe_dir);
+        }
+        dirs
+    }
+
+    /// Header to write before the entries, if the format needs one. GNU
+    /// manifests get an `# algorithms:` header when they hold several digests
+    /// per line or tree hashes, which could be taken for plain XXH3 otherwise.
+    pub fn header(&self, format: ManifestFormat) -> Option<String> {
+        match format {
+            ManifestFormat::Gnu if self.algos.len() > 1 || self.algos.iter().any(|a| a.is_tree()) => {
+                let names: Vec<&str> = self.algos.iter().map(|a| a.name()).collect();
+                Some(format!("{ALGORITHMS_HEADER} {}\n", names.join(" ")))
+            }
+            ManifestFormat::Sfv => Some(format!("; Generated by zhash-core {}\n", env!("CARGO_PKG_VERSION"))),
+            _ => None,
+        }
+    }
+
+    /// Sorts entries and links by path.
+    pub fn sort(&mut self, order: SortOrder) {
+        self.entries.sort_by(|a, b| order.compare(&a.path, &b.path));
+        self.links.sort_by(|a, b| order.compare(&a.path, &b.path));
+    }
+
+    /// Writes the header, then every entry and link in `format`.
+    pub fn write_to(&self, format: ManifestFormat, out: &mut impl Write) -> io::Result<()> {
+        if let Some(header) = self.header(format) {
+            out.write_all(header.as_bytes())?;
+        }
+        for entry in &self.entries {
+            out.write_all(entry.format(format)?.as_bytes())?;
+        }
+        for link in &self.links {
+            out.write_all(link.format(format).as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+/// Digest columns of the manifest at `path` whose `content` starts with the
+/// lines up to its first entry; see [`Manifest::load`].
+fn column_algos(path: &Path, content: &str, algo: Option<HashAlgo>) -> io::Result<Vec<HashAlgo>> {
+    let first_line = content.lines().map(str::trim).find(|l| !l.is_empty() && !l.starts_with('#'));
+    let algos = match header_algos(content) {
+        Some(algos) => algos,
+        None if first_line.and_then(ManifestEntry::parse_tagged_line).is_some() => algo.into_iter().collect(),
+        None => {
+            let algo = algo
+                .or_else(|| HashAlgo::from_manifest_path(path))
+                .or_else(|| HashAlgo::from_digest(first_line?.trim_start_matches('\\').split(' ').next()?))
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Cannot determine the hash algorithm of {}", path.display()),
+                    )
+                })?;
+            vec![algo]
+        }
+    };
+    Ok(algos)
+}
+
+/// Passes `visit` each line of `reader`, decoded lossily as UTF-8, without
+/// its line ending.
+pub(crate) fn read_lines(mut reader: impl BufRead, mut visit: impl FnMut(&str) -> io::Result<()>) -> io::Result<()> {
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            return Ok(());
+        }
+        let text = String::from_utf8_lossy(&line);
+        visit(text.strip_suffix('\n').map_or(&*text, |text| text.strip_suffix('\r').unwrap_or(text)))?;
+    }
+}
+
+/// Digest columns announced by an `# algorithms:` header, if present and valid.
+fn header_algos(content: &str) -> Option<Vec<HashAlgo>> {
+    let names = content
+        .lines()
+        .map(str::trim)
+        .take_while(|line| line.is_empty() || line.starts_with('#'))
+        .find_map(|line| line.strip_prefix(ALGORITHMS_HEADER))?;
+    let algos: Option<Vec<HashAlgo>> = names.split_whitespace().map(HashAlgo::from_name).collect();
+    algos.filter(|algos| !algos.is_empty())
+}
+
+/// Path of `file` relative to the directory `root`, `/`-separated, going
+/// through `..` when `file` lies outside of `root`.
+pub fn relative_name(root: &Path, file: &Path) -> String {
+    let root = absolute_normalized(root);
+    let file = absolute_normalized(file);
+    let root: Vec<Component> = root.components().collect();
+    let file: Vec<Component> = file.components().collect();
+
+    let common = root.iter().zip(&file).take_while(|(a, b)| a == b).count();
+    let mut parts: Vec<String> = vec!["..".to_string(); root.len() - common];
+    parts.extend(file[common..].iter().map(|c| c.as_os_str().to_string_lossy().into_owned()));
+    parts.join("/")
+}
+
+/// Whether `hash` looks like a hex digest, CRC32's optional `0x` prefix aside.
+fn is_hex_digest(hash: &str) -> bool {
+    let hex = hash.strip_prefix("0x").unwrap_or(hash);
+    !hex.is_empty() && hex.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Compares `a` and `b` run by run, digit runs by numeric value.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a.as_bytes(), b.as_bytes());
+    loop {
+        match (a.first(), b.first()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let (da, ra) = split_digits(a);
+                let (db, rb) = split_digits(b);
+                let (va, vb) = (trim_zeros(da), trim_zeros(db));
+                let order = va.len().cmp(&vb.len()).then_with(|| va.cmp(vb));
+                if order != Ordering::Equal {
+                    return order;
+                }
+                (a, b) = (ra, rb);
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(y);
+                }
+                (a, b) = (&a[1..], &b[1..]);
+            }
+        }
+    }
+}
+
+fn split_digits(s: &[u8]) -> (&[u8], &[u8]) {
+    s.split_at(s.iter().take_while(|c| c.is_ascii_digit()).count())
+}
+
+fn trim_zeros(digits: &[u8]) -> &[u8] {
+    let zeros = digits.iter().take_while(|&&c| c == b'0').count();
+    &digits[zeros..]
+}
+
+/// Escapes a file name the way GNU coreutils does (`\\`, `\n`, `\r`).
+pub(crate) fn escape_name(name: &str) -> String {
+    let mut escaped = String::with_capacity(name.len());
+    for c in name.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Reverses [`escape_name`]; `None` on an invalid escape sequence.
+pub(crate) fn unescape_name(name: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(name.len());
+    let mut chars = name.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next()? {
+            '\\' => unescaped.push('\\'),
+            'n' => unescaped.push('\n'),
+            'r' => unescaped.push('\r'),
+            _ => return None,
+        }
+    }
+    Some(unescaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NAMES: &[&str] = &["plain.txt", "dir/with space", "back\\slash", "new\nline", "cr\rname", "\\\\\n"];
+
+    fn entry(path: &str, digests: &[(HashAlgo, &str)]) -> ManifestEntry {
+        ManifestEntry::new(path.to_string(), digests.iter().map(|(algo, d)| (*algo, d.to_string())).collect())
+    }
+
+    #[test]
+    fn escaped_names_round_trip() {
+        for name in NAMES {
+            assert_eq!(unescape_name(&escape_name(name)).as_deref(), Some(*name));
+        }
+        assert_eq!(escape_name("a\\b\nc"), "a\\\\b\\nc");
+    }
+
+    #[test]
+    fn gnu_lines_round_trip() {
+        let algos = [HashAlgo::Xxh3, HashAlgo::Sha1];
+        for name in NAMES {
+            for binary in [true, false] {
+                let mut written = entry(name, &[(HashAlgo::Xxh3, "0123456789abcdef"), (HashAlgo::Sha1, &"ab".repeat(20))]);
+                written.binary = binary;
+                let line = written.to_line();
+                let parsed = ManifestEntry::parse_line(line.trim_end_matches('\n'), &algos);
+                assert_eq!(parsed, Some(written), "{line:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn tagged_lines_round_trip() {
+        for name in NAMES {
+            let written = entry(name, &[(HashAlgo::Sha256, &"0f".repeat(32))]);
+            let line = written.to_tagged_lines();
+            assert_eq!(ManifestEntry::parse_tagged_line(line.trim_end_matches('\n')), Some(written), "{line:?}");
+        }
+        // Several digests of one path come back as one entry
+        let written = entry("dir/file", &[(HashAlgo::Xxh3, "0123456789abcdef"), (HashAlgo::Md5, &"1a".repeat(16))]);
+        let manifest = Manifest::parse(&written.to_tagged_lines(), Vec::new());
+        assert_eq!(manifest.entries, vec![written]);
+        assert_eq!(manifest.algos, vec![HashAlgo::Xxh3, HashAlgo::Md5]);
+    }
+
+    #[test]
+    fn sfv_lines_round_trip() {
+        let written = entry("dir/with space.bin", &[(HashAlgo::Crc32, "00c0ffee")]);
+        let line = written.to_sfv_line().unwrap();
+        assert_eq!(line, "dir/with space.bin 00C0FFEE\n");
+        assert_eq!(ManifestEntry::parse_sfv_line(line.trim_end(), false), Some(written));
+        assert!(entry("back\\slash", &[(HashAlgo::Crc32, "00c0ffee")]).to_sfv_line().is_err());
+        assert_eq!(
+            ManifestEntry::parse_sfv_line("dir\\file.bin 00C0FFEE", true).map(|e| e.path),
+            Some("dir/file.bin".to_string())
+        );
+    }
+
+    #[test]
+    fn natural_order_compares_digit_runs_by_value() {
+        let mut names = vec!["file10", "file2", "file02", "file1", "file", "file1a", "a10b2", "a10b10"];
+        names.sort_by(|a, b| SortOrder::Natural.compare(a, b));
+        assert_eq!(names, ["a10b2", "a10b10", "file", "file1", "file1a", "file02", "file2", "file10"]);
+        for (a, b) in names.iter().zip(&names[1..]) {
+            assert_eq!(SortOrder::Natural.compare(b, a), Ordering::Greater, "{a} < {b}");
+        }
+    }
+
+    #[test]
+    fn malformed_lines_are_skipped() {
+        let algos = [HashAlgo::Xxh3];
+        assert_eq!(ManifestEntry::parse_line("0123456789abcdeg *file", &algos), None);
+        assert_eq!(ManifestEntry::parse_line("[ERROR] ./file: Permission denied", &algos), None);
+        assert_eq!(ManifestEntry::parse_line("\\0123456789abcdef *bad\\escape", &algos), None);
+        assert_eq!(ManifestEntry::parse_line("0123456789abcdef *", &algos), None);
+        assert_eq!(ManifestEntry::parse_tagged_line("XXH3 (file) = not-hex"), None);
+        assert_eq!(ManifestEntry::parse_tagged_line("\\XXH3 (bad\\escape) = 0123456789abcdef"), None);
+
+        let content = "0123456789abcdef *good\n\
+                       0123456789abcdeg *non-hex\n\
+                       [ERROR] ./unreadable: Permission denied\n\
+                       \\0123456789abcdef *bad\\escape\n\
+                       fedcba9876543210  text mode\n";
+        let manifest = Manifest::parse(content, algos.to_vec());
+        let paths: Vec<&str> = manifest.entries.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, ["good", "text mode"]);
+    }
+}
@@ -0,0 +1,157 @@
+use std::path::{Path, PathBuf};
+
+/// Order in which files are read, so that a cold scan of a spinning disk
+/// moves its heads forward rather than back and forth.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum ReadOrder {
+    /// As the walk finds them or the manifest lists them.
+    #[default]
+    Listed,
+    /// By device, then inode number, which on ext4 and XFS roughly follows
+    /// the position of the data.
+    Inode,
+    /// By device, then position of the first extent on disk (Linux FIEMAP);
+    /// by inode for files without one, or where FIEMAP is not supported.
+    Physical,
+}
+
+/// Sort key placing `path` in `order`, or `None` if it cannot be read; such
+/// files come first, which reports their errors early.
+///
+/// The key is `(device, has_extent, position)`: byte offsets and inode
+/// numbers are never compared, files without an extent (empty or inline
+/// ones, or all of them under [`ReadOrder::Inode`]) coming first on their
+/// device, by inode.
+pub fn read_position(path: &Path, order: ReadOrder) -> Option<(u64, bool, u64)> {
+    if order == ReadOrder::Listed {
+        return Some((0, false, 0));
+    }
+    let metadata = path.metadata().ok()?;
+    let (dev, ino) = device_and_inode(&metadata);
+    let physical = match order {
+        ReadOrder::Physical => first_extent_offset(path),
+        _ => None,
+    };
+    Some(match physical {
+        Some(offset) => (dev, true, offset),
+        None => (dev, false, ino),
+    })
+}
+
+/// `path` as an [`crate::extsort::ExternalSorter`] record sorting it into
+/// `order`, for lists of files too long to sort in memory: its
+/// [`read_position`] as fixed-width hex, so that byte-wise order is numeric
+/// order, then the path. See [`record_path`].
+pub fn position_record(path: &Path, order: ReadOrder) -> Vec<String> {
+    let key = match read_position(path, order) {
+        Some((dev, has_extent, position)) => format!("{dev:016x}{}{position:016x}", u8::from(has_extent)),
+        None => String::new(),
+    };
+    path_record(key, path)
+}
+
+/// `path` as a record sorted by `key`, which [`record_path`] turns back
+/// into `path` whatever its encoding.
+pub fn path_record(key: String, path: &Path) -> Vec<String> {
+    match path.to_str() {
+        Some(path) => vec![key, path.to_string()],
+        // Not valid UTF-8: the raw bytes in hex, in a third field
+        None => vec![key, String::new(), hex_bytes(path)],
+    }
+}
+
+/// Path of a record made by [`position_record`] or [`path_record`].
+pub fn record_path(record: &[String]) -> PathBuf {
+    match record {
+        [_, _, hex] => path_from_hex(hex),
+        [_, path, ..] => PathBuf::from(path),
+        _ => PathBuf::new(),
+    }
+}
+
+#[cfg(unix)]
+fn hex_bytes(path: &Path) -> String {
+    use std::os::unix::ffi::OsStrExt;
+    path.as_os_str().as_bytes().iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(unix)]
+fn path_from_hex(hex: &str) -> PathBuf {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+    let bytes: Vec<u8> = (0..hex.len() / 2)
+        .filter_map(|i| u8::from_str_radix(hex.get(2 * i..2 * i + 2)?, 16).ok())
+        .collect();
+    PathBuf::from(OsStr::from_bytes(&bytes))
+}
+
+// Elsewhere such paths are kept lossily, as manifest names are
+#[cfg(not(unix))]
+fn hex_bytes(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+#[cfg(not(unix))]
+fn path_from_hex(hex: &str) -> PathBuf {
+    PathBuf::from(hex)
+}
+
+#[cfg(unix)]
+fn device_and_inode(metadata: &std::fs::Metadata) -> (u64, u64) {
+    use std::os::unix::fs::MetadataExt;
+    (metadata.dev(), metadata.ino())
+}
+
+#[cfg(not(unix))]
+fn device_and_inode(_metadata: &std::fs::Metadata) -> (u64, u64) {
+    (0, 0)
+}
+
+/// Physical byte offset of the first extent of `path`, as reported by the
+/// `FS_IOC_FIEMAP` ioctl.
+#[cfg(target_os = "linux")]
+fn first_extent_offset(path: &Path) -> Option<u64> {
+    use std::fs::File;
+    use std::os::fd::AsRawFd;
+
+    /// `struct fiemap` from `linux/fiemap.h`, with room for one extent.
+    #[repr(C)]
+    #[derive(Default)]
+    struct Fiemap {
+        start: u64,
+        length: u64,
+        flags: u32,
+        mapped_extents: u32,
+        extent_count: u32,
+        reserved: u32,
+        extent: FiemapExtent,
+    }
+
+    /// `struct fiemap_extent`.
+    #[repr(C)]
+    #[derive(Default)]
+    struct FiemapExtent {
+        logical: u64,
+        physical: u64,
+        length: u64,
+        reserved64: [u64; 2],
+        flags: u32,
+        reserved: [u32; 3],
+    }
+
+    /// `_IOWR('f', 11, struct fiemap)`
+    const FS_IOC_FIEMAP: u64 = 0xc020_660b;
+
+    let file = File::open(path).ok()?;
+    let mut fiemap = Fiemap { length: u64::MAX, extent_count: 1, ..Fiemap::default() };
+    // SAFETY: `fiemap` is a valid `struct fiemap` followed by the single
+    // extent `extent_count` announces, which is all the kernel writes.
+    let result = unsafe { libc::ioctl(file.as_raw_fd(), FS_IOC_FIEMAP as _, &mut fiemap as *mut Fiemap) };
+    (result == 0 && fiemap.mapped_extents > 0).then_some(fiemap.extent.physical)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn first_extent_offset(_path: &Path) -> Option<u64> {
+    None
+}
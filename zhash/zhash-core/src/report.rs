@@ -0,0 +1,129 @@
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::verify::{FileCheck, FileStatus, Summary, Verifier, VerifyExit};
+
+/// One verification run, possibly over several manifests.
+#[derive(Debug, Default, Serialize)]
+pub struct Report {
+    pub manifests: Vec<ManifestReport>,
+    pub totals: Summary,
+    pub exit_code: u8,
+}
+
+/// Results for a single manifest.
+#[derive(Debug, Serialize)]
+pub struct ManifestReport {
+    pub manifest: PathBuf,
+    /// Algorithms checked; empty when the manifest could not be loaded (see `error`).
+    pub algorithms: Vec<&'static str>,
+    pub base_path: Option<PathBuf>,
+    pub elapsed_secs: f64,
+    pub totals: Summary,
+    /// Why the manifest could not be loaded.
+    pub error: Option<String>,
+    pub files: Vec<FileReport>,
+}
+
+/// Verification result for a single file.
+#[derive(Debug, Serialize)]
+pub struct FileReport {
+    pub path: String,
+    pub hashes: Vec<HashReport>,
+    /// Expected target, for a symbolic link.
+    pub link_target: Option<String>,
+    pub status: Option<FileStatus>,
+    pub size: Option<u64>,
+    pub elapsed_secs: Option<f64>,
+    pub error: Option<String>,
+}
+
+/// Expected and computed digest of one file for one algorithm.
+#[derive(Debug, Serialize)]
+pub struct HashReport {
+    pub algorithm: &'static str,
+    pub expected_hash: String,
+    pub computed_hash: Option<String>,
+}
+
+impl From<&FileCheck> for FileReport {
+    fn from(check: &FileCheck) -> Self {
+        let hashes = check
+            .expected
+            .iter()
+            .map(|(algo, expected)| HashReport {
+                algorithm: algo.display_name(),
+                expected_hash: expected.clone(),
+                computed_hash: check
+                    .computed
+                    .iter()
+                    .find(|(a, _)| a == algo)
+                    .map(|(_, digest)| digest.clone()),
+            })
+            .collect();
+        Self {
+            path: check.path.clone(),
+            hashes,
+            link_target: check.link_target.clone(),
+            status: check.status.clone(),
+            size: check.size,
+            elapsed_secs: check.elapsed.map(|d| d.as_secs_f64()),
+            error: check.error.clone(),
+        }
+    }
+}
+
+impl ManifestReport {
+    /// Snapshot of a finished [`Verifier`] run over `manifest`.
+    pub fn from_verifier(manifest: &Path, verifier: &Verifier, elapsed: Duration) -> Self {
+        Self {
+            files: verifier.files.iter().map(FileReport::from).collect(),
+            ..Self::totals_only(manifest, verifier, elapsed)
+        }
+    }
+
+    /// Like [`ManifestReport::from_verifier`] without the per-file details,
+    /// which would copy every entry of a huge manifest for nothing when only
+    /// the totals are used.
+    pub fn totals_only(manifest: &Path, verifier: &Verifier, elapsed: Duration) -> Self {
+        Self {
+            manifest: manifest.to_path_buf(),
+            algorithms: verifier.algos.iter().map(|a| a.display_name()).collect(),
+            base_path: Some(verifier.base_path.clone()),
+            elapsed_secs: elapsed.as_secs_f64(),
+            totals: verifier.summary(),
+            error: None,
+            files: Vec::new(),
+        }
+    }
+
+    /// Entry for a manifest that could not be found or loaded.
+    pub fn failed(manifest: &Path, error: &io::Error) -> Self {
+        Self {
+            manifest: manifest.to_path_buf(),
+            algorithms: Vec::new(),
+            base_path: None,
+            elapsed_secs: 0.0,
+            totals: Summary::default(),
+            error: Some(error.to_string()),
+            files: Vec::new(),
+        }
+    }
+}
+
+impl Report {
+    pub fn push(&mut self, manifest: ManifestReport) {
+        self.totals += manifest.totals;
+        self.manifests.push(manifest);
+    }
+
+    /// Records the final exit status and writes the report as pretty JSON.
+    pub fn write_json(&mut self, exit: VerifyExit, out: &mut impl Write) -> io::Result<()> {
+        self.exit_code = exit.code();
+        serde_json::to_writer_pretty(&mut *out, self)?;
+        writeln!(out)
+    }
+}
@@ -0,0 +1,58 @@
+use std::path::{Path, PathBuf};
+
+use crate::algo::HashAlgo;
+use crate::cache::CACHE_FILE_NAME;
+use crate::manifest::{MULTI_MANIFEST_NAME, SFV_MANIFEST_NAME};
+use crate::stamp::STAMPS_SUFFIX;
+
+/// Suffix of the temporary file a manifest is written to before replacing it.
+pub const TMP_SUFFIX: &str = ".tmp";
+
+/// Suffix of the previous version of a manifest, kept on request.
+pub const BACKUP_SUFFIX: &str = ".bak";
+
+/// Suffix of the journal kept next to a manifest while it is generated.
+pub const JOURNAL_SUFFIX: &str = ".journal";
+
+/// Suffix of the journal kept next to a manifest while it is verified.
+pub const VERIFY_JOURNAL_SUFFIX: &str = ".verify.journal";
+
+/// Suffix of the log of read errors met while generating a manifest.
+pub const ERROR_LOG_SUFFIX: &str = ".errors.log";
+
+/// Every suffix zhash appends to a manifest's name for its own files.
+pub const SIDECAR_SUFFIXES: &[&str] =
+    &[TMP_SUFFIX, BACKUP_SUFFIX, STAMPS_SUFFIX, JOURNAL_SUFFIX, VERIFY_JOURNAL_SUFFIX, ERROR_LOG_SUFFIX];
+
+/// Exact names of the files zhash writes next to conventionally named
+/// manifests: the manifests themselves, each with every one of
+/// [`SIDECAR_SUFFIXES`], and the hash cache. Real files that merely look
+/// alike, such as `report.bak`, are not among them.
+pub fn sidecar_names() -> Vec<String> {
+    let manifests = HashAlgo::ALL
+        .iter()
+        .map(|algo| algo.manifest_name())
+        .chain([SFV_MANIFEST_NAME, MULTI_MANIFEST_NAME]);
+    let mut names = Vec::new();
+    for manifest in manifests {
+        names.push(manifest.to_string());
+        names.extend(SIDECAR_SUFFIXES.iter().map(|suffix| format!("{manifest}{suffix}")));
+    }
+    names.push(CACHE_FILE_NAME.to_string());
+    names.push(format!("{CACHE_FILE_NAME}{TMP_SUFFIX}"));
+    names
+}
+
+/// `path` with `suffix` appended to its file name, for the files kept next
+/// to a manifest: `with_suffix("CRC.xxhash3", ".bak")` is `CRC.xxhash3.bak`.
+pub fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Default log of the read errors met while generating `manifest`:
+/// `CRC.xxhash3.errors.log`.
+pub fn error_log_path(manifest: &Path) -> PathBuf {
+    with_suffix(manifest, ERROR_LOG_SUFFIX)
+}
@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+use std::fs::{self, File, Metadata};
+use std::io::{self, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::manifest::{escape_name, read_lines, unescape_name};
+use crate::sidecar::with_suffix;
+
+/// Suffix appended to a manifest's filename for its stamp file,
+/// e.g. `CRC.xxhash3.stamps`.
+pub const STAMPS_SUFFIX: &str = ".stamps";
+
+/// Size and modification time of a file when it was hashed. A file whose
+/// stamp hasn't changed since is assumed to still have the same digest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FileStamp {
+    pub size: u64,
+    /// Nanoseconds since the Unix epoch, negative before it.
+    pub mtime_ns: i128,
+}
+
+impl FileStamp {
+    pub fn from_metadata(metadata: &Metadata) -> io::Result<Self> {
+        let mtime_ns = match metadata.modified()?.duration_since(UNIX_EPOCH) {
+            Ok(after) => after.as_nanos() as i128,
+            Err(before) => -(before.duration().as_nanos() as i128),
+        };
+        Ok(Self { size: metadata.len(), mtime_ns })
+    }
+
+    pub fn of(path: &Path) -> io::Result<Self> {
+        Self::from_metadata(&fs::metadata(path)?)
+    }
+}
+
+/// Stamps of the files of a manifest, keyed by manifest path, kept in a
+/// sidecar file next to it so that the manifest itself stays readable by
+/// `sha256sum -c` and friends.
+///
+/// One `size mtime_ns path` line per file, the path escaped as in GNU
+/// manifests.
+#[derive(Debug, Default)]
+pub struct Stamps {
+    pub files: HashMap<String, FileStamp>,
+}
+
+impl Stamps {
+    /// Stamp file belonging to the manifest at `manifest`.
+    pub fn path_for(manifest: &Path) -> PathBuf {
+        with_suffix(manifest, STAMPS_SUFFIX)
+    }
+
+    /// Reads the stamp file at `path`; a missing file yields no stamps.
+    /// Malformed lines are skipped.
+    pub fn load(path: &Path) -> io::Result<Stamps> {
+        let mut stamps = Stamps::default();
+        Stamps::read(path, |file, stamp| {
+            stamps.insert(file, stamp);
+            Ok(())
+        })?;
+        Ok(stamps)
+    }
+
+    /// Passes `visit` each file of the stamp file at `path` in turn, without
+    /// holding them all in memory, like [`Stamps::load`] otherwise.
+    pub fn read(path: &Path, mut visit: impl FnMut(String, FileStamp) -> io::Result<()>) -> io::Result<()> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        read_lines(BufReader::new(file), |line| {
+            let parsed = (|| {
+                let (size, rest) = line.split_once(' ')?;
+                let (mtime_ns, path) = rest.split_once(' ')?;
+                let stamp = FileStamp { size: size.parse().ok()?, mtime_ns: mtime_ns.parse().ok()? };
+                Some((unescape_name(path)?, stamp))
+            })();
+            match parsed {
+                Some((file, stamp)) => visit(file, stamp),
+                None => Ok(()),
+            }
+        })
+    }
+
+    pub fn get(&self, path: &str) -> Option<FileStamp> {
+        self.files.get(path).copied()
+    }
+
+    pub fn insert(&mut self, path: String, stamp: FileStamp) {
+        self.files.insert(path, stamp);
+    }
+
+    /// Writes one line per file, sorted by path.
+    pub fn write_to(&self, out: &mut impl Write) -> io::Result<()> {
+        let mut paths: Vec<&String> = self.files.keys().collect();
+        paths.sort();
+        for path in paths {
+            out.write_all(Stamps::format_line(path, self.files[path]).as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Stamp file line for `path`, for writers streaming them one by one.
+    pub fn format_line(path: &str, stamp: FileStamp) -> String {
+        format!("{} {} {}\n", stamp.size, stamp.mtime_ns, escape_name(path))
+    }
+}
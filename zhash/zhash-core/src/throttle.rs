@@ -0,0 +1,159 @@
+use std::io;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often the load average is looked at by [`Throttle::consume`].
+const LOAD_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long hashing pauses before the load average is looked at again.
+const LOAD_PAUSE: Duration = Duration::from_secs(5);
+
+/// Slows hashing down for background runs: caps the read rate across all
+/// threads, and pauses while the machine is busy. Does nothing by default.
+#[derive(Debug, Default)]
+pub struct Throttle {
+    rate: Option<TokenBucket>,
+    max_load: Option<f64>,
+    last_load_check: Mutex<Option<Instant>>,
+}
+
+impl Throttle {
+    /// Throttle reading at most `max_rate` bytes per second, and pausing
+    /// while the 1-minute load average exceeds `max_load`.
+    pub fn new(max_rate: Option<u64>, max_load: Option<f64>) -> Self {
+        Self {
+            rate: max_rate.filter(|&rate| rate > 0).map(TokenBucket::new),
+            max_load,
+            last_load_check: Mutex::new(None),
+        }
+    }
+
+    /// Accounts for `bytes` just read, sleeping as long as the limits require.
+    pub fn consume(&self, bytes: u64) {
+        if let Some(max_load) = self.max_load {
+            self.wait_for_load(max_load);
+        }
+        if let Some(rate) = &self.rate {
+            rate.take(bytes);
+        }
+    }
+
+    /// Pauses until the load is back under `max_load`. The time of the last
+    /// check that found it so is only updated then, which makes every other
+    /// reader check the load, and pause, until it is.
+    fn wait_for_load(&self, max_load: f64) {
+        loop {
+            {
+                let mut last = self.last_load_check.lock().unwrap();
+                if last.is_some_and(|last| last.elapsed() < LOAD_CHECK_INTERVAL) {
+                    return;
+                }
+                if !load_average().is_some_and(|load| load > max_load) {
+                    *last = Some(Instant::now());
+                    return;
+                }
+            }
+            // Outside of the lock, so that readers do not queue behind a sleeper
+            thread::sleep(LOAD_PAUSE);
+        }
+    }
+}
+
+/// Token bucket shared by every thread: each read takes its size in tokens,
+/// refilled at `rate` per second up to one second's worth. A read larger
+/// than what is left runs into debt, which the reader sleeps off.
+#[derive(Debug)]
+struct TokenBucket {
+    rate: f64,
+    state: Mutex<(f64, Instant)>,
+}
+
+impl TokenBucket {
+    fn new(rate: u64) -> Self {
+        Self { rate: rate as f64, state: Mutex::new((rate as f64, Instant::now())) }
+    }
+
+    fn take(&self, bytes: u64) {
+        let wait = {
+            let mut state = self.state.lock().unwrap();
+            let (tokens, last) = &mut *state;
+            let now = Instant::now();
+            *tokens = (*tokens + now.duration_since(*last).as_secs_f64() * self.rate).min(self.rate);
+            *last = now;
+            *tokens -= bytes as f64;
+            (*tokens < 0.0).then(|| Duration::from_secs_f64(-*tokens / self.rate))
+        };
+        if let Some(wait) = wait {
+            thread::sleep(wait);
+        }
+    }
+}
+
+/// Parses a rate in bytes per second: a number, optionally followed by `K`,
+/// `M`, `G` or `T` (powers of 1024), e.g. `50M`.
+pub fn parse_rate(rate: &str) -> Result<u64, String> {
+    let rate = rate.trim();
+    let digits = rate.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let shift = match rate[digits.len()..].to_ascii_uppercase().as_str() {
+        "" | "B" => 0,
+        "K" | "KB" | "KIB" => 10,
+        "M" | "MB" | "MIB" => 20,
+        "G" | "GB" | "GIB" => 30,
+        "T" | "TB" | "TIB" => 40,
+        _ => return Err(format!("unknown unit in {rate}, expected K, M, G or T")),
+    };
+    let value: u64 = digits.parse().map_err(|_| format!("expected a number of bytes per second: {rate}"))?;
+    value
+        .checked_mul(1 << shift)
+        .ok_or_else(|| format!("rate too large: {rate}"))
+}
+
+/// 1-minute load average, where the system reports one.
+#[cfg(target_os = "linux")]
+fn load_average() -> Option<f64> {
+    std::fs::read_to_string("/proc/loadavg")
+        .ok()?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn load_average() -> Option<f64> {
+    None
+}
+
+/// Lowers the CPU priority of the process to `nice` (0 to 19), if given,
+/// and, with `idle_io`, puts its reads in the idle I/O class, served only
+/// when no other process uses the disk. Without `idle_io`, I/O priority follows the
+/// CPU one, as `ionice` documents.
+///
+/// Threads inherit these settings, so this must run before any is spawned.
+#[cfg(target_os = "linux")]
+pub fn lower_priority(nice: Option<i32>, idle_io: bool) -> io::Result<()> {
+    /// From `linux/ioprio.h`.
+    const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+    const IOPRIO_CLASS_IDLE: libc::c_int = 3;
+    const IOPRIO_CLASS_SHIFT: libc::c_int = 13;
+
+    // SAFETY (both calls): plain system calls on the current process, without pointers
+    if let Some(nice) = nice
+        && unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, nice) } != 0
+    {
+        return Err(io::Error::last_os_error());
+    }
+    if idle_io {
+        let ioprio = IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT;
+        if unsafe { libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, 0, ioprio) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn lower_priority(_nice: Option<i32>, _idle_io: bool) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "process priority can only be lowered on Linux"))
+}
@@ -0,0 +1,183 @@
+use std::fs::File;
+use std::io;
+use std::sync::Mutex;
+
+use rayon::prelude::*;
+use xxhash_rust::xxh3::{Xxh3, xxh3_64};
+
+use crate::hash::CHUNK_SIZE;
+
+/// Size of the leaves [`HashAlgo::Xxh3Tree`](crate::HashAlgo::Xxh3Tree)
+/// hashes independently. Part of the algorithm's definition: changing it
+/// changes every digest.
+pub const TREE_LEAF_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Streaming XXH3 tree hash: every [`TREE_LEAF_SIZE`] leaf is hashed with
+/// XXH3-64, and the root digest is the XXH3-64 of the leaf digests followed by
+/// the total length, all little-endian. An empty input has one empty leaf.
+///
+/// Fed sequentially it gives the same digest as [`tree_hash_file`], which
+/// hashes leaves in parallel.
+pub struct TreeHasher {
+    leaf: Xxh3,
+    leaf_len: u64,
+    leaves: Vec<u64>,
+    total: u64,
+}
+
+impl TreeHasher {
+    pub fn new() -> Self {
+        Self { leaf: Xxh3::new(), leaf_len: 0, leaves: Vec::new(), total: 0 }
+    }
+
+    pub fn update(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            let room = (TREE_LEAF_SIZE - self.leaf_len).min(data.len() as u64) as usize;
+            self.leaf.update(&data[..room]);
+            self.leaf_len += room as u64;
+            self.total += room as u64;
+            data = &data[room..];
+            if self.leaf_len == TREE_LEAF_SIZE {
+                self.leaves.push(self.leaf.digest());
+                self.leaf.reset();
+                self.leaf_len = 0;
+            }
+        }
+    }
+
+    pub fn finalize(mut self) -> String {
+        if self.leaf_len > 0 || self.leaves.is_empty() {
+            self.leaves.push(self.leaf.digest());
+        }
+        root_digest(&self.leaves, self.total)
+    }
+}
+
+impl Default for TreeHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Tree hash of an in-memory buffer, leaves hashed in parallel.
+pub fn tree_hash_bytes(data: &[u8]) -> String {
+    if data.is_empty() {
+        return root_digest(&[xxh3_64(data)], 0);
+    }
+    let leaves: Vec<u64> = data.par_chunks(TREE_LEAF_SIZE as usize).map(xxh3_64).collect();
+    root_digest(&leaves, data.len() as u64)
+}
+
+/// Tree hash of the first `size` bytes of `file`, leaves read and hashed in
+/// parallel with `parallel`, one after the other otherwise, calling
+/// `progress(read, size)` as bytes are hashed.
+///
+/// Fails if the file ends before `size` bytes, e.g. when it is truncated
+/// while being hashed.
+pub fn tree_hash_file(
+    file: &File,
+    size: u64,
+    parallel: bool,
+    progress: impl FnMut(u64, u64) + Send,
+) -> io::Result<String> {
+    if size == 0 {
+        return Ok(tree_hash_bytes(&[]));
+    }
+    let progress = Mutex::new((0u64, progress));
+    let hash_leaf = |index: u64| {
+        let start = index * TREE_LEAF_SIZE;
+        let end = (start + TREE_LEAF_SIZE).min(size);
+        let mut leaf = Xxh3::new();
+        let mut buffer = vec![0u8; CHUNK_SIZE.min((end - start) as usize)];
+        let mut offset = start;
+        while offset < end {
+            let len = buffer.len().min((end - offset) as usize);
+            read_exact_at(file, &mut buffer[..len], offset)?;
+            leaf.update(&buffer[..len]);
+            offset += len as u64;
+            let mut progress = progress.lock().unwrap();
+            progress.0 += len as u64;
+            let read = progress.0;
+            (progress.1)(read, size);
+        }
+        Ok(leaf.digest())
+    };
+    let count = size.div_ceil(TREE_LEAF_SIZE);
+    let leaves = if parallel {
+        (0..count).into_par_iter().map(hash_leaf).collect::<io::Result<Vec<u64>>>()?
+    } else {
+        (0..count).map(hash_leaf).collect::<io::Result<Vec<u64>>>()?
+    };
+    Ok(root_digest(&leaves, size))
+}
+
+fn root_digest(leaves: &[u64], total: u64) -> String {
+    let mut bytes = Vec::with_capacity((leaves.len() + 1) * 8);
+    for leaf in leaves {
+        bytes.extend_from_slice(&leaf.to_le_bytes());
+    }
+    bytes.extend_from_slice(&total.to_le_bytes());
+    format!("{:016x}", xxh3_64(&bytes))
+}
+
+#[cfg(unix)]
+fn read_exact_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<()> {
+    use std::os::unix::fs::FileExt;
+    file.read_exact_at(buf, offset)
+}
+
+#[cfg(windows)]
+fn read_exact_at(file: &File, mut buf: &mut [u8], mut offset: u64) -> io::Result<()> {
+    use std::os::windows::fs::FileExt;
+    while !buf.is_empty() {
+        match file.seek_read(buf, offset)? {
+            0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+            n => {
+                buf = &mut buf[n..];
+                offset += n as u64;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Every way of computing the tree hash gives the same digest for inputs
+    /// at and around the leaf boundaries.
+    #[test]
+    fn hashers_agree_around_leaf_boundaries() {
+        let leaf = TREE_LEAF_SIZE as usize;
+        let path = std::env::temp_dir().join(format!("zhash-tree-{}", std::process::id()));
+        let data: Vec<u8> = (0..3 * leaf + 12345).map(|i| (i * 31 % 251) as u8).collect();
+        for size in [0, leaf, leaf + 1, 3 * leaf + 12345] {
+            let data = &data[..size];
+            let expected = tree_hash_bytes(data);
+
+            let mut hasher = TreeHasher::new();
+            // Uneven chunks, so that updates straddle leaf boundaries
+            for chunk in data.chunks(CHUNK_SIZE - 7) {
+                hasher.update(chunk);
+            }
+            assert_eq!(hasher.finalize(), expected, "TreeHasher, {size} bytes");
+
+            fs::write(&path, data).unwrap();
+            let file = File::open(&path).unwrap();
+            for parallel in [true, false] {
+                let mut last = 0;
+                let digest = tree_hash_file(&file, size as u64, parallel, |read, _| last = read).unwrap();
+                assert_eq!(digest, expected, "tree_hash_file, {size} bytes, parallel: {parallel}");
+                assert_eq!(last, size as u64);
+            }
+        }
+        fs::remove_file(&path).unwrap();
+
+        // A single leaf: the XXH3 of its XXH3 and the length
+        let mut root = xxh3_64(&data[..leaf]).to_le_bytes().to_vec();
+        root.extend_from_slice(&(leaf as u64).to_le_bytes());
+        assert_eq!(tree_hash_bytes(&data[..leaf]), format!("{:016x}", xxh3_64(&root)));
+    }
+}
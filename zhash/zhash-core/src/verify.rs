@@ -0,0 +1,641 @@
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::algo::HashAlgo;
+use crate::device::DeviceLimits;
+use crate::hash::{IoOptions, hash_file_multi_with_progress};
+use crate::journal::Journal;
+use crate::manifest::{Manifest, relative_name};
+use crate::order::{ReadOrder, read_position};
+use crate::sidecar::sidecar_names;
+use crate::throttle::Throttle;
+use crate::walk::{WalkOptions, absolute_normalized, walk_tree};
+
+/// Outcome of checking one manifest entry against the filesystem.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum FileStatus {
+    Ok,
+    Corrupted,
+    Missing,
+    Error,
+    /// Present on disk but not listed in the manifest.
+    Untracked,
+}
+
+impl FileStatus {
+    pub fn symbol(&self) -> &str {
+        match self {
+            FileStatus::Ok => "✓",
+            FileStatus::Corrupted => "✗",
+            FileStatus::Missing => "?",
+            FileStatus::Error => "!",
+            FileStatus::Untracked => "+",
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        match self {
+            FileStatus::Ok => "OK",
+            FileStatus::Corrupted => "CORRUPTED",
+            FileStatus::Missing => "MISSING",
+            FileStatus::Error => "ERROR",
+            FileStatus::Untracked => "UNTRACKED",
+        }
+    }
+
+    /// Status whose [`FileStatus::text`] is `text`.
+    pub fn from_text(text: &str) -> Option<Self> {
+        [FileStatus::Ok, FileStatus::Corrupted, FileStatus::Missing, FileStatus::Error, FileStatus::Untracked]
+            .into_iter()
+            .find(|status| status.text() == text)
+    }
+
+    /// ANSI color escape used when printing this status to a terminal.
+    pub fn color(&self) -> &str {
+        match self {
+            FileStatus::Ok => "\x1b[32m",      // Green
+            FileStatus::Corrupted | FileStatus::Error => "\x1b[31m", // Red
+            FileStatus::Missing => "\x1b[33m",   // Yellow
+            FileStatus::Untracked => "\x1b[36m", // Cyan
+        }
+    }
+}
+
+/// A manifest entry together with its verification status, once known.
+#[derive(Debug, Clone)]
+pub struct FileCheck {
+    pub path: String,
+    /// Digests to check, one per algorithm.
+    pub expected: Vec<(HashAlgo, String)>,
+    /// Target a symbolic link listed in the manifest must point to; such
+    /// entries have no digest and are checked with `read_link` alone.
+    pub link_target: Option<String>,
+    pub status: Option<FileStatus>,
+    /// Digests computed during verification, if the file could be read.
+    pub computed: Vec<(HashAlgo, String)>,
+    pub size: Option<u64>,
+    /// Time spent hashing the file.
+    pub elapsed: Option<Duration>,
+    /// Read error, for [`FileStatus::Error`], or where a link points
+    /// instead, for a [`FileStatus::Corrupted`] one.
+    pub error: Option<String>,
+}
+
+impl FileCheck {
+    pub fn new(path: String, expected: Vec<(HashAlgo, String)>) -> Self {
+        Self {
+            path,
+            expected,
+            link_target: None,
+            status: None,
+            computed: Vec::new(),
+            size: None,
+            elapsed: None,
+            error: None,
+        }
+    }
+
+    /// Stores the result of [`verify_file`] on this entry.
+    pub fn record(&mut self, outcome: FileOutcome) {
+        self.status = Some(outcome.status);
+        self.computed = outcome.computed;
+        self.size = outcome.size;
+        self.elapsed = Some(outcome.elapsed);
+        self.error = outcome.error;
+    }
+}
+
+/// Everything learned about one file by [`verify_file`].
+#[derive(Debug, Clone)]
+pub struct FileOutcome {
+    pub status: FileStatus,
+    pub computed: Vec<(HashAlgo, String)>,
+    pub size: Option<u64>,
+    pub elapsed: Duration,
+    pub error: Option<String>,
+}
+
+impl FileOutcome {
+    fn new(status: FileStatus, start: Instant) -> Self {
+        Self {
+            status,
+            computed: Vec::new(),
+            size: None,
+            elapsed: start.elapsed(),
+            error: None,
+        }
+    }
+}
+
+/// Receives per-file events while [`Verifier::verify_files`] runs.
+///
+/// Calls arrive concurrently from rayon worker threads.
+pub trait VerifyObserver: Sync {
+    /// Called as a file is read; `percent` only moves forward in steps of at
+    /// least half a percent, and always reaches 100.
+    fn on_progress(&self, _index: usize, _check: &FileCheck, _percent: f64) {}
+
+    /// Called once a file's final status is known.
+    fn on_file_done(&self, _index: usize, _check: &FileCheck, _status: &FileStatus) {}
+}
+
+/// Observer that ignores every event.
+impl VerifyObserver for () {}
+
+/// Status tallies over a verification run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Summary {
+    pub ok: usize,
+    pub corrupted: usize,
+    pub missing: usize,
+    pub errors: usize,
+    pub untracked: usize,
+    pub total: usize,
+}
+
+impl Summary {
+    pub fn from_checks(files: &[FileCheck]) -> Self {
+        let mut summary = Summary { total: files.len(), ..Summary::default() };
+        for file in files {
+            match file.status {
+                Some(FileStatus::Ok) => summary.ok += 1,
+                Some(FileStatus::Corrupted) => summary.corrupted += 1,
+                Some(FileStatus::Missing) => summary.missing += 1,
+                Some(FileStatus::Error) => summary.errors += 1,
+                Some(FileStatus::Untracked) => summary.untracked += 1,
+                None => {}
+            }
+        }
+        summary
+    }
+
+    pub fn has_problems(&self) -> bool {
+        self.corrupted > 0 || self.missing > 0 || self.errors > 0 || self.untracked > 0
+    }
+
+    /// Exit code for this run, assuming every manifest loaded fine.
+    pub fn exit_status(&self) -> VerifyExit {
+        if self.corrupted > 0 {
+            VerifyExit::Corrupted
+        } else if self.errors > 0 {
+            VerifyExit::ReadError
+        } else if self.missing > 0 {
+            VerifyExit::Missing
+        } else if self.untracked > 0 {
+            VerifyExit::Untracked
+        } else {
+            VerifyExit::Ok
+        }
+    }
+}
+
+/// Process exit codes of the verifiers, derived from a run's [`Summary`].
+///
+/// When several apply, the first one in this list wins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum VerifyExit {
+    /// A manifest could not be found, opened or parsed (4).
+    ManifestError = 4,
+    /// At least one file does not match its expected digest (1).
+    Corrupted = 1,
+    /// At least one file exists but could not be read (3).
+    ReadError = 3,
+    /// At least one listed file does not exist (2).
+    Missing = 2,
+    /// Files not listed in the manifest were found, when looking for them (5).
+    Untracked = 5,
+    /// Every listed file matched (0).
+    Ok = 0,
+}
+
+impl VerifyExit {
+    /// One-line description per code, for `--help` texts.
+    pub const HELP: &'static str = "Exit codes:\n  0  all files OK\n  1  corrupted files found\n  2  missing files\n  3  read errors\n  4  manifest not found or unreadable\n  5  untracked files found (with --untracked)";
+
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+}
+
+impl From<VerifyExit> for std::process::ExitCode {
+    fn from(exit: VerifyExit) -> Self {
+        std::process::ExitCode::from(exit.code())
+    }
+}
+
+impl std::ops::AddAssign for Summary {
+    fn add_assign(&mut self, other: Summary) {
+        self.ok += other.ok;
+        self.corrupted += other.corrupted;
+        self.missing += other.missing;
+        self.errors += other.errors;
+        self.untracked += other.untracked;
+        self.total += other.total;
+    }
+}
+
+/// Checks the files listed in a manifest against their expected digests.
+#[derive(Default)]
+pub struct Verifier {
+    /// Directory manifest paths are resolved against.
+    pub base_path: PathBuf,
+    pub files: Vec<FileCheck>,
+    /// Algorithms being checked.
+    pub algos: Vec<HashAlgo>,
+    /// How files are read.
+    pub io: IoOptions,
+    /// How many files are read at once from each device.
+    pub devices: DeviceLimits,
+    /// Order in which files are read.
+    pub read_order: ReadOrder,
+    /// Limits on the read rate and on when reading may happen.
+    pub throttle: Throttle,
+}
+
+impl Verifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads the manifest at `path`; entries resolve relative to its directory.
+    ///
+    /// See [`Manifest::load`] for how `algo` is detected when not given.
+    pub fn load_hash_file(&mut self, path: &Path, algo: Option<HashAlgo>) -> io::Result<()> {
+        let manifest = Manifest::load(path, algo)?;
+        let base_path = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        self.set_manifest(manifest, base_path);
+        Ok(())
+    }
+
+    /// Replaces the files to check with the entries and links of `manifest`.
+    pub fn set_manifest(&mut self, manifest: Manifest, base_path: PathBuf) {
+        self.algos = manifest.algos;
+        self.base_path = base_path;
+        let links = manifest.links.into_iter().map(|link| FileCheck {
+            link_target: Some(link.target),
+            ..FileCheck::new(link.path, Vec::new())
+        });
+        self.files = manifest
+            .entries
+            .into_iter()
+            .map(|entry| FileCheck::new(entry.path, entry.digests))
+            .chain(links)
+            .collect();
+    }
+
+    /// Restricts checking to the digests of `algos`; the others are ignored.
+    ///
+    /// Files with no digest left fail with [`FileStatus::Error`].
+    pub fn select_algos(&mut self, algos: &[HashAlgo]) {
+        self.algos.retain(|algo| algos.contains(algo));
+        for check in &mut self.files {
+            check.expected.retain(|(algo, _)| algos.contains(algo));
+        }
+    }
+
+    /// Finds the first manifest named after one of `algos` (or a multi-algorithm
+    /// manifest) in `dirs` and loads it.
+    ///
+    /// Returns the path of the manifest that was loaded.
+    pub fn auto_load_hash_file(&mut self, dirs: &[PathBuf], algos: &[HashAlgo]) -> io::Result<PathBuf> {
+        let (path, algo) = Manifest::find(dirs, algos).ok_or_else(|| {
+            let names: Vec<_> = algos.iter().map(|a| a.manifest_name()).collect();
+            io::Error::new(io::ErrorKind::NotFound, format!("No {} file found", names.join(", ")))
+        })?;
+        self.load_hash_file(&path, algo)?;
+        Ok(path)
+    }
+
+    /// Absolute or base-relative location of a manifest entry on disk.
+    pub fn resolve(&self, check: &FileCheck) -> PathBuf {
+        resolve_in(&self.base_path, check)
+    }
+
+    /// Hashes every listed file in parallel and records its status in place.
+    pub fn verify_files(&mut self, observer: &impl VerifyObserver) {
+        let (base_path, io, devices, throttle) = (&self.base_path, self.io, &self.devices, &self.throttle);
+        for_each_file(&mut self.files, base_path, self.read_order, |index, check| {
+            let outcome = check_file(base_path, io, devices, throttle, index, check, observer);
+            observer.on_file_done(index, check, &outcome.status);
+            check.record(outcome);
+        });
+    }
+
+    /// Like [`Verifier::verify_files`], logging every outcome to the journal
+    /// at `journal_path` as it is known. With `resume`, files whose outcome a
+    /// previous run over the same manifest recorded there are not hashed
+    /// again, and end up exactly as that run left them.
+    ///
+    /// Fails only if the journal cannot be opened. Once it is, the journal is
+    /// a mere shortcut: write errors are ignored, and it is removed when
+    /// every file has been checked.
+    pub fn verify_files_journaled(
+        &mut self,
+        observer: &impl VerifyObserver,
+        journal_path: &Path,
+        resume: bool,
+    ) -> io::Result<()> {
+        let names: Vec<&str> = self.algos.iter().map(|a| a.name()).collect();
+        let header = format!("# zhsh journal: {}", names.join(" "));
+        let (journal, records) = Journal::open(journal_path, &header, resume)?;
+        let done: HashMap<String, (String, FileOutcome)> = records.iter().filter_map(|r| parse_outcome_record(r)).collect();
+
+        let journal = Mutex::new(journal);
+        let (base_path, io, devices, throttle) = (&self.base_path, self.io, &self.devices, &self.throttle);
+        for_each_file(&mut self.files, base_path, self.read_order, |index, check| {
+            let expected = expected_key(check);
+            let outcome = match done.get(&check.path) {
+                Some((done_expected, outcome)) if *done_expected == expected => outcome.clone(),
+                _ => {
+                    let outcome = check_file(base_path, io, devices, throttle, index, check, observer);
+                    let _ = journal.lock().unwrap().record(&outcome_record(&check.path, expected, &outcome));
+                    outcome
+                }
+            };
+            observer.on_file_done(index, check, &outcome.status);
+            check.record(outcome);
+        });
+        let _ = journal.into_inner().unwrap().remove();
+        Ok(())
+    }
+
+    /// Walks the tree covered by the manifest with `options` and appends
+    /// every file it does not list as [`FileStatus::Untracked`]. Files in
+    /// `ignore` (typically the running executable) are skipped, and so are
+    /// the files of [`sidecar_names`] and the `.zhashignore` rules of
+    /// [`walk_tree`].
+    ///
+    /// The tree is `base_path`, or the ancestor of it reached by the longest
+    /// run of leading `..` among the entries, so that the usual
+    /// `../file` manifests written in a subdirectory cover their parent.
+    ///
+    /// Returns the globs of [`WalkOptions::include`] that matched no file.
+    /// Fails if part of the tree could not be walked, after appending the
+    /// untracked files found elsewhere.
+    pub fn find_untracked(&mut self, ignore: &[PathBuf], options: &WalkOptions) -> io::Result<Vec<String>> {
+        let ups = self
+            .files
+            .iter()
+            .map(|check| Path::new(&check.path).components().take_while(|c| *c == Component::ParentDir).count())
+            .max()
+            .unwrap_or(0);
+        // A manifest named without a directory has an empty base path, which cannot be walked
+        let base = if self.base_path.as_os_str().is_empty() { Path::new(".") } else { self.base_path.as_path() };
+        let mut root = base.to_path_buf();
+        for _ in 0..ups {
+            root.push("..");
+        }
+
+        let listed: HashSet<PathBuf> = self.files.iter().map(|check| absolute_normalized(&self.resolve(check))).collect();
+        let mut options = options.clone();
+        options.exclude.extend(sidecar_names());
+        let tree = walk_tree(&root, ignore, &options)?;
+        for file in tree.files {
+            if !listed.contains(&absolute_normalized(&file)) {
+                let mut check = FileCheck::new(relative_name(base, &file), Vec::new());
+                check.status = Some(FileStatus::Untracked);
+                check.size = file.metadata().ok().map(|m| m.len());
+                self.files.push(check);
+            }
+        }
+        match tree.errors.as_slice() {
+            [] => Ok(tree.unmatched),
+            [error] => Err(io::Error::other(error.clone())),
+            [error, rest @ ..] => Err(io::Error::other(format!("{error} (and {} more)", rest.len()))),
+        }
+    }
+
+    pub fn summary(&self) -> Summary {
+        Summary::from_checks(&self.files)
+    }
+}
+
+/// Calls `visit` on every entry and its index, in parallel, starting them in
+/// `order`.
+fn for_each_file(
+    files: &mut [FileCheck],
+    base_path: &Path,
+    order: ReadOrder,
+    visit: impl Fn(usize, &mut FileCheck) + Sync + Send,
+) {
+    if order == ReadOrder::Listed {
+        files.par_iter_mut().enumerate().for_each(|(index, check)| visit(index, check));
+        return;
+    }
+    let mut files: Vec<(usize, &mut FileCheck)> = files.iter_mut().enumerate().collect();
+    files.sort_by_cached_key(|(_, check)| read_position(&resolve_in(base_path, check), order));
+    // Handed out one at a time, where splitting the list would start threads far apart on disk
+    files.into_iter().par_bridge().for_each(|(index, check)| visit(index, check));
+}
+
+/// Hashes one entry once its device lets it be read, within `throttle`,
+/// reporting progress to `observer` in 0.5% steps.
+fn check_file(
+    base_path: &Path,
+    io: IoOptions,
+    devices: &DeviceLimits,
+    throttle: &Throttle,
+    index: usize,
+    check: &FileCheck,
+    observer: &impl VerifyObserver,
+) -> FileOutcome {
+    let full_path = resolve_in(base_path, check);
+    if let Some(target) = &check.link_target {
+        let outcome = verify_link(&full_path, target);
+        observer.on_progress(index, check, 100.0);
+        return outcome;
+    }
+    let permit = full_path.metadata().ok().and_then(|metadata| devices.acquire(&metadata));
+    let io = io.holding_permit(permit.is_some());
+    let mut last_percent = 0.0;
+    let mut throttled = 0;
+    verify_file(&full_path, &check.expected, io, |read, total| {
+        throttle.consume(read.saturating_sub(throttled));
+        throttled = read;
+        let percent = if total > 0 {
+            (read as f64 / total as f64) * 100.0
+        } else {
+            100.0
+        };
+        if total > 0 && (percent - last_percent > 0.5 || percent == 100.0) {
+            last_percent = percent;
+            observer.on_progress(index, check, percent);
+        }
+    })
+}
+
+/// Expected digests or link target of an entry as one string, telling
+/// whether a journaled outcome was for the same ones.
+fn expected_key(check: &FileCheck) -> String {
+    if let Some(target) = &check.link_target {
+        return format!("link={target}");
+    }
+    let digests: Vec<String> = check.expected.iter().map(|(algo, digest)| format!("{}={digest}", algo.name())).collect();
+    digests.join(" ")
+}
+
+/// Journal record of an outcome: path, expected digests, status, size,
+/// elapsed nanoseconds, error, then one `algo=digest` field per computed digest.
+fn outcome_record(path: &str, expected: String, outcome: &FileOutcome) -> Vec<String> {
+    let mut record = vec![
+        path.to_string(),
+        expected,
+        outcome.status.text().to_string(),
+        outcome.size.map(|size| size.to_string()).unwrap_or_default(),
+        outcome.elapsed.as_nanos().to_string(),
+        outcome.error.clone().unwrap_or_default(),
+    ];
+    record.extend(outcome.computed.iter().map(|(algo, digest)| format!("{}={digest}", algo.name())));
+    record
+}
+
+/// Reverses [`outcome_record`], as `(path, (expected, outcome))`.
+fn parse_outcome_record(record: &[String]) -> Option<(String, (String, FileOutcome))> {
+    let [path, expected, status, size, elapsed, error, computed @ ..] = record else {
+        return None;
+    };
+    let computed = computed
+        .iter()
+        .map(|field| {
+            let (algo, digest) = field.split_once('=')?;
+            Some((HashAlgo::from_name(algo)?, digest.to_string()))
+        })
+        .collect::<Option<Vec<_>>>()?;
+    let outcome = FileOutcome {
+        status: FileStatus::from_text(status)?,
+        computed,
+        size: if size.is_empty() { None } else { Some(size.parse().ok()?) },
+        elapsed: Duration::from_nanos(elapsed.parse().ok()?),
+        error: (!error.is_empty()).then(|| error.clone()),
+    };
+    Some((path.clone(), (expected.clone(), outcome)))
+}
+
+fn resolve_in(base_path: &Path, check: &FileCheck) -> PathBuf {
+    let file_path = PathBuf::from(&check.path);
+    if file_path.is_absolute() {
+        file_path
+    } else {
+        base_path.join(file_path)
+    }
+}
+
+/// Hashes the file at `full_path` once and compares it with every digest of
+/// `expected`; the file is OK only if all of them match.
+pub fn verify_file(
+    full_path: &Path,
+    expected: &[(HashAlgo, String)],
+    io: IoOptions,
+    progress: impl FnMut(u64, u64) + Send,
+) -> FileOutcome {
+    let start = Instant::now();
+    if !full_path.exists() {
+        return FileOutcome::new(FileStatus::Missing, start);
+    }
+    if expected.is_empty() {
+        return FileOutcome {
+            error: Some("no digest to check for the selected algorithms".to_string()),
+            ..FileOutcome::new(FileStatus::Error, start)
+        };
+    }
+    let size = full_path.metadata().ok().map(|m| m.len());
+    let algos: Vec<HashAlgo> = expected.iter().map(|(algo, _)| *algo).collect();
+    let mut outcome = match hash_file_multi_with_progress(full_path, &algos, io, progress) {
+        Ok(actual) => {
+            let all_match = expected
+                .iter()
+                .zip(&actual)
+                .all(|((algo, want), got)| algo.digests_match(want, got));
+            let status = if all_match {
+                FileStatus::Ok
+            } else {
+                FileStatus::Corrupted
+            };
+            FileOutcome {
+                computed: algos.into_iter().zip(actual).collect(),
+                ..FileOutcome::new(status, start)
+            }
+        }
+        Err(e) => FileOutcome {
+            error: Some(e.to_string()),
+            ..FileOutcome::new(FileStatus::Error, start)
+        },
+    };
+    outcome.size = size;
+    outcome
+}
+
+/// Checks that the symbolic link at `full_path` still points to `target`,
+/// as recorded: [`FileStatus::Missing`] if it is gone, and
+/// [`FileStatus::Corrupted`] if it points elsewhere or is no longer a link.
+pub fn verify_link(full_path: &Path, target: &str) -> FileOutcome {
+    let start = Instant::now();
+    match full_path.read_link() {
+        Ok(actual) if actual.to_string_lossy() == target => FileOutcome::new(FileStatus::Ok, start),
+        Ok(actual) => FileOutcome {
+            error: Some(format!("link points to {}", actual.display())),
+            ..FileOutcome::new(FileStatus::Corrupted, start)
+        },
+        Err(e) if e.kind() == io::ErrorKind::NotFound => FileOutcome::new(FileStatus::Missing, start),
+        Err(_) if full_path.symlink_metadata().is_ok() => FileOutcome {
+            error: Some("no longer a symbolic link".to_string()),
+            ..FileOutcome::new(FileStatus::Corrupted, start)
+        },
+        Err(e) => FileOutcome {
+            error: Some(e.to_string()),
+            ..FileOutcome::new(FileStatus::Error, start)
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::IoThreads;
+    use crate::tree::TREE_LEAF_SIZE;
+    use std::fs;
+    use std::sync::mpsc;
+
+    /// Tree hashes of files spanning several leaves, read under a single
+    /// permit per device: nested parallel leaves used to let a worker waiting
+    /// on them pick up another file's job, which then waited for the permit
+    /// forever.
+    #[test]
+    fn tree_hashes_under_one_permit_do_not_deadlock() {
+        let dir = std::env::temp_dir().join(format!("zhash-verify-permit-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut files = Vec::new();
+        for i in 0..12u8 {
+            let data = vec![i; (2 * TREE_LEAF_SIZE + 1) as usize];
+            let name = format!("file{i}");
+            fs::write(dir.join(&name), &data).unwrap();
+            files.push(FileCheck::new(name, vec![(HashAlgo::Xxh3Tree, HashAlgo::Xxh3Tree.hash_bytes(&data))]));
+        }
+        let mut verifier = Verifier {
+            base_path: dir.clone(),
+            files,
+            algos: vec![HashAlgo::Xxh3Tree],
+            devices: DeviceLimits::new(&[IoThreads { path: None, threads: 1 }]).unwrap(),
+            ..Verifier::default()
+        };
+
+        let (done_tx, done_rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let pool = rayon::ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+            pool.install(|| verifier.verify_files(&()));
+            done_tx.send(verifier.summary()).unwrap();
+        });
+        let summary = done_rx.recv_timeout(Duration::from_secs(60));
+        let _ = fs::remove_dir_all(&dir);
+        let summary = summary.expect("verification deadlocked");
+        assert_eq!(summary.ok, 12);
+    }
+}
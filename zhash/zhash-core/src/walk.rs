@@ -0,0 +1,307 @@
+use std::collections::HashSet;
+use std::fs::FileType;
+use std::io;
+use std::ops::ControlFlow;
+use std::path::{Component, Path, PathBuf};
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::overrides::{Override, OverrideBuilder};
+use ignore::{DirEntry, WalkBuilder};
+
+/// Per-directory ignore file, in `.gitignore` syntax, honored by every walk.
+pub const IGNORE_FILE_NAME: &str = ".zhashignore";
+
+/// Entries skipped unless [`WalkOptions::default_excludes`] is turned off:
+/// VCS metadata and files desktop environments drop in every folder.
+pub const DEFAULT_EXCLUDES: &[&str] = &[".git", ".svn", ".hg", "Thumbs.db", "desktop.ini", ".DS_Store"];
+
+/// What a walk does with symbolic links.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum SymlinkPolicy {
+    /// Leave links out, reporting them as skipped.
+    #[default]
+    Skip,
+    /// Walk through links as if they were their target; loops are
+    /// detected and reported as errors.
+    Follow,
+    /// List links with their target, without following them.
+    Record,
+}
+
+/// Kind of a directory entry a walk leaves out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpecialKind {
+    Symlink,
+    Fifo,
+    Socket,
+    BlockDevice,
+    CharDevice,
+    Other,
+}
+
+impl SpecialKind {
+    #[cfg(unix)]
+    fn of(file_type: FileType) -> Self {
+        use std::os::unix::fs::FileTypeExt;
+        if file_type.is_symlink() {
+            SpecialKind::Symlink
+        } else if file_type.is_fifo() {
+            SpecialKind::Fifo
+        } else if file_type.is_socket() {
+            SpecialKind::Socket
+        } else if file_type.is_block_device() {
+            SpecialKind::BlockDevice
+        } else if file_type.is_char_device() {
+            SpecialKind::CharDevice
+        } else {
+            SpecialKind::Other
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn of(file_type: FileType) -> Self {
+        if file_type.is_symlink() { SpecialKind::Symlink } else { SpecialKind::Other }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SpecialKind::Symlink => "symlink",
+            SpecialKind::Fifo => "fifo",
+            SpecialKind::Socket => "socket",
+            SpecialKind::BlockDevice => "block device",
+            SpecialKind::CharDevice => "character device",
+            SpecialKind::Other => "special file",
+        }
+    }
+}
+
+/// Which files of a tree a walk returns.
+///
+/// Globs use `.gitignore` syntax relative to the walk's root: `*.tmp`
+/// matches at any depth, `/build` only at the top, `cache/` only directories.
+#[derive(Debug, Clone)]
+pub struct WalkOptions {
+    /// When not empty, only files matching one of these globs, or inside a
+    /// directory matching one, are kept.
+    pub include: Vec<String>,
+    /// Files and directories matching one of these globs are skipped; they
+    /// take precedence over `include`.
+    pub exclude: Vec<String>,
+    /// Also honor `.gitignore` files (and the repository's `info/exclude`).
+    pub gitignore: bool,
+    /// Skip [`DEFAULT_EXCLUDES`].
+    pub default_excludes: bool,
+    pub symlinks: SymlinkPolicy,
+    /// Skip dot files and directories (and, on Windows, hidden ones), even
+    /// those matching `include`.
+    pub skip_hidden: bool,
+    /// Do not descend into directories on another filesystem than `root`.
+    pub one_file_system: bool,
+}
+
+impl Default for WalkOptions {
+    fn default() -> Self {
+        Self {
+            include: Vec::new(),
+            exclude: Vec::new(),
+            gitignore: false,
+            default_excludes: true,
+            symlinks: SymlinkPolicy::Skip,
+            skip_hidden: false,
+            one_file_system: false,
+        }
+    }
+}
+
+/// One entry reported by [`walk`].
+#[derive(Debug)]
+pub enum WalkItem {
+    /// Regular file, or link to one under [`SymlinkPolicy::Follow`].
+    File(PathBuf),
+    /// Link and its unresolved target, under [`SymlinkPolicy::Record`].
+    Link(PathBuf, PathBuf),
+    /// Entry left out because it is not a regular file: FIFO, socket,
+    /// device, or link under [`SymlinkPolicy::Skip`].
+    Skipped(PathBuf, SpecialKind),
+    /// Entry that could not be read, symlink loops included.
+    Error(String),
+    /// Glob of [`WalkOptions::include`] that matched nothing, reported once
+    /// the whole tree has been walked.
+    Unmatched(String),
+}
+
+/// Everything a walk found, sorted by what is to be done with it.
+#[derive(Debug, Default)]
+pub struct Tree {
+    pub files: Vec<PathBuf>,
+    pub links: Vec<(PathBuf, PathBuf)>,
+    pub skipped: Vec<(PathBuf, SpecialKind)>,
+    pub errors: Vec<String>,
+    pub unmatched: Vec<String>,
+}
+
+/// Regular files under `root` selected by `options`; see [`walk`].
+pub fn collect_files(root: &Path, exclude: &[PathBuf], options: &WalkOptions) -> io::Result<Vec<PathBuf>> {
+    Ok(walk_tree(root, exclude, options)?.files)
+}
+
+/// Collects the whole of [`walk`] in memory.
+pub fn walk_tree(root: &Path, exclude: &[PathBuf], options: &WalkOptions) -> io::Result<Tree> {
+    let mut tree = Tree::default();
+    walk(root, exclude, options, |item| {
+        match item {
+            WalkItem::File(path) => tree.files.push(path),
+            WalkItem::Link(path, target) => tree.links.push((path, target)),
+            WalkItem::Skipped(path, kind) => tree.skipped.push((path, kind)),
+            WalkItem::Error(error) => tree.errors.push(error),
+            WalkItem::Unmatched(glob) => tree.unmatched.push(glob),
+        }
+        ControlFlow::Continue(())
+    })?;
+    Ok(tree)
+}
+
+/// Walks `root`, passing `visit` each entry selected by `options`, as it is
+/// found, until `visit` breaks. Entries listed in `exclude` (compared as
+/// absolute, normalized paths) are skipped, directories with everything
+/// below them.
+///
+/// Fails only on an invalid glob; unreadable entries are reported as
+/// [`WalkItem::Error`].
+pub fn walk(
+    root: &Path,
+    exclude: &[PathBuf],
+    options: &WalkOptions,
+    mut visit: impl FnMut(WalkItem) -> ControlFlow<()>,
+) -> io::Result<()> {
+    let defaults: &[&str] = if options.default_excludes { DEFAULT_EXCLUDES } else { &[] };
+    let excludes = defaults
+        .iter()
+        .copied()
+        .chain(options.exclude.iter().map(String::as_str))
+        .map(|glob| format!("!{glob}"));
+    let overrides = build_overrides(root, excludes)?;
+    // Applied to what the walker yields rather than given to it: a whitelist
+    // override would win over the ignore files and the hidden filter
+    let include = build_includes(root, &options.include)?;
+    let mut matched = HashSet::new();
+
+    let exclude: HashSet<PathBuf> = exclude.iter().map(|p| absolute_normalized(p)).collect();
+    let mut builder = WalkBuilder::new(root);
+    builder
+        .standard_filters(false)
+        .hidden(options.skip_hidden)
+        .git_ignore(options.gitignore)
+        .git_exclude(options.gitignore)
+        .require_git(false)
+        .add_custom_ignore_filename(IGNORE_FILE_NAME)
+        .follow_links(options.symlinks == SymlinkPolicy::Follow)
+        .same_file_system(options.one_file_system)
+        .overrides(overrides);
+    if !exclude.is_empty() {
+        builder.filter_entry(move |entry| !exclude.contains(&absolute_normalized(entry.path())));
+    }
+
+    for entry in builder.build() {
+        let item = match entry {
+            Ok(entry) => match classify(entry, options.symlinks) {
+                Some(item) if included(&include, &item, &mut matched) => item,
+                _ => continue,
+            },
+            Err(e) => WalkItem::Error(e.to_string()),
+        };
+        if visit(item).is_break() {
+            return Ok(());
+        }
+    }
+    for glob in options.include.iter().filter(|glob| !matched.contains(glob.as_str())) {
+        if visit(WalkItem::Unmatched(glob.clone())).is_break() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Gitignore-style matcher for `globs`, relative to `root`.
+fn build_overrides(root: &Path, globs: impl Iterator<Item = String>) -> io::Result<Override> {
+    let mut builder = OverrideBuilder::new(root);
+    for glob in globs {
+        builder
+            .add(&glob)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?;
+    }
+    builder
+        .build()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))
+}
+
+/// Matcher for [`WalkOptions::include`], relative to `root`. Files it
+/// ignores are the ones included; `!` globs whitelist them back out.
+fn build_includes(root: &Path, globs: &[String]) -> io::Result<Gitignore> {
+    let mut builder = GitignoreBuilder::new(root);
+    for glob in globs {
+        builder
+            .add_line(None, glob)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?;
+    }
+    builder
+        .build()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))
+}
+
+/// Whether `item` passes [`WalkOptions::include`], matched against its path
+/// and then each of its parent directories, closest first; everything does
+/// when it is empty, errors always do. The globs that decided are added to
+/// `matched`.
+fn included(include: &Gitignore, item: &WalkItem, matched: &mut HashSet<String>) -> bool {
+    let path = match item {
+        WalkItem::File(path) | WalkItem::Link(path, _) | WalkItem::Skipped(path, _) => path,
+        WalkItem::Error(_) | WalkItem::Unmatched(_) => return true,
+    };
+    if include.is_empty() {
+        return true;
+    }
+    let decision = include.matched_path_or_any_parents(path, false);
+    if let Some(glob) = decision.inner() {
+        matched.insert(glob.original().to_string());
+    }
+    decision.is_ignore()
+}
+
+/// What to report for `entry`; `None` for directories, which the walker
+/// descends into.
+fn classify(entry: DirEntry, symlinks: SymlinkPolicy) -> Option<WalkItem> {
+    // Only the standard input entry has no type, and it is never walked
+    let file_type = entry.file_type()?;
+    let item = if file_type.is_file() {
+        WalkItem::File(entry.into_path())
+    } else if file_type.is_dir() {
+        return None;
+    } else if file_type.is_symlink() && symlinks == SymlinkPolicy::Record {
+        match entry.path().read_link() {
+            Ok(target) => WalkItem::Link(entry.into_path(), target),
+            Err(e) => WalkItem::Error(format!("{}: {e}", entry.path().display())),
+        }
+    } else {
+        WalkItem::Skipped(entry.into_path(), SpecialKind::of(file_type))
+    };
+    Some(item)
+}
+
+/// `path` made absolute with `.` and `..` components resolved lexically,
+/// without touching the filesystem (symlinks are not followed).
+pub fn absolute_normalized(path: &Path) -> PathBuf {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
@@ -1,15 +1,32 @@
-use clap::{Parser, ValueEnum};
+use clap::Parser;
 use rayon::prelude::*;
 use std::{
+    collections::HashMap,
     fs::{self, File},
-    io::{self, Read, Write},
+    io::{self, BufWriter, Write},
+    ops::ControlFlow,
     path::{Path, PathBuf},
+    process::ExitCode,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+    thread,
     time::Instant,
 };
-use walkdir::WalkDir;
 use indicatif::{ProgressBar, ProgressStyle};
-
-const DEFAULT_FULL_LOAD_LIMIT: u64 = 200 * 1024 * 1024;
+use zhash_core::atomic::{AtomicFile, backup_path, tmp_path};
+use zhash_core::cache::{CACHE_FILE_NAME, CacheKey, HashCache};
+use zhash_core::device::{DeviceLimits, IoThreads};
+use zhash_core::extsort::{DEFAULT_RUN_LEN, ExternalSorter};
+use zhash_core::hash::hash_file_multi;
+use zhash_core::journal::Journal;
+use zhash_core::manifest::relative_name;
+use zhash_core::order::{ReadOrder, path_record, position_record, record_path};
+use zhash_core::sidecar::{JOURNAL_SUFFIX, error_log_path, with_suffix};
+use zhash_core::stamp::{FileStamp, Stamps};
+use zhash_core::walk::{SpecialKind, SymlinkPolicy, WalkItem, WalkOptions, walk};
+use zhash_core::{DEFAULT_FULL_LOAD_LIMIT, HashAlgo, IoOptions, IoStrategy, LinkEntry, Manifest, ManifestEntry, ManifestFormat, SortOrder};
 
 #[derive(Parser)]
 struct Args {
@@ -17,87 +34,449 @@ struct Args {
     source: PathBuf,
     #[arg(short, long, default_value = "./xxHash")]
     output_dir: PathBuf,
-    #[arg(short, long, default_value = "CRC.xxhash3")]
-    name: String,
+    /// Nom du manifeste (défaut : CRC.<algo>, par ex. CRC.xxhash3, ou CRC.multi avec plusieurs algorithmes)
+    #[arg(short, long)]
+    name: Option<String>,
+    /// Répertoire de référence des chemins écrits (défaut : le répertoire de sortie)
+    #[arg(long)]
+    root: Option<PathBuf>,
+    /// Marqueur de mode texte (`  `) au lieu de binaire (` *`), comme `sha256sum --text`
+    #[arg(long)]
+    text: bool,
+    /// Format des lignes : `gnu` (digest *chemin), `tagged` (ALGO (chemin) = digest) ou `sfv` (chemin CRC32)
+    #[arg(long, value_enum, default_value_t = ManifestFormat::Gnu)]
+    format: ManifestFormat,
+    /// Taille maximale (octets) des fichiers chargés entiers en mémoire par `--io auto`
     #[arg(long, default_value_t = DEFAULT_FULL_LOAD_LIMIT)]
     full_load_limit: u64,
+    /// Lecture des fichiers : `auto` (chargés entiers jusqu'à --full-load-limit, lus par blocs
+    /// au-delà), `read` (par blocs de 1 Mio), `mmap` (projetés en mémoire sur les montages en lecture seule, lus par blocs ailleurs) ou `whole` (chargés entiers)
+    #[arg(long, value_enum, default_value_t = IoStrategy::Auto)]
+    io: IoStrategy,
+    /// Fichiers lus en même temps sur un périphérique : `N` pour tous, `CHEMIN=N` pour celui
+    /// qui contient CHEMIN, 0 pour aucune limite ; répétable (défaut : 1 sur les disques
+    /// rotatifs et USB, sans limite ailleurs)
+    #[arg(long, value_name = "[CHEMIN=]N")]
+    io_threads: Vec<IoThreads>,
+    /// Ordre de lecture : `listed` (celui du parcours), `inode` ou `physical` (premier extent sur
+    /// le disque, Linux seulement) ; les deux derniers accélèrent les lectures à froid sur disque
+    /// rotatif, mais attendent la fin du parcours pour commencer
+    #[arg(long, value_enum, default_value_t = ReadOrder::Listed)]
+    read_order: ReadOrder,
     #[arg(long, default_value_t = num_cpus::get())]
     threads: usize,
-    #[arg(long, value_enum, default_value_t = HashAlgo::Xxh3)]
-    algo: HashAlgo,
-}
-
-#[derive(Copy, Clone, ValueEnum)]
-enum HashAlgo {
-    Crc32,
-    Md5,
-    Xxh3,
+    /// Algorithme(s), séparés par des virgules : plusieurs empreintes par fichier en une seule lecture
+    /// (défaut : xxh3, ou crc32 pour --format sfv)
+    #[arg(long, value_enum, value_delimiter = ',')]
+    algo: Vec<HashAlgo>,
+    /// Met à jour le manifeste existant : seuls les fichiers nouveaux ou modifiés (taille ou date
+    /// de modification différente) sont rehachés, les fichiers supprimés sont retirés
+    #[arg(long)]
+    update: bool,
+    /// Reprend les empreintes du cache du répertoire de sortie pour les fichiers inchangés
+    /// (même inode, taille et date de modification), et le met à jour
+    #[arg(long, conflicts_with = "ignore_cache")]
+    trust_cache: bool,
+    /// Rehache tout sans consulter le cache, puis le reconstruit
+    #[arg(long)]
+    ignore_cache: bool,
+    /// Ne retient que les fichiers correspondant à ce motif (syntaxe `.gitignore`, répétable)
+    #[arg(long)]
+    include: Vec<String>,
+    /// Ignore les fichiers et répertoires correspondant à ce motif (syntaxe `.gitignore`, répétable)
+    #[arg(long)]
+    exclude: Vec<String>,
+    /// Respecte aussi les fichiers `.gitignore` (les `.zhashignore` le sont toujours)
+    #[arg(long)]
+    gitignore: bool,
+    /// N'ignore pas par défaut `.git`, `Thumbs.db`, `.DS_Store`, etc.
+    #[arg(long)]
+    no_default_excludes: bool,
+    /// Liens symboliques : `skip` (ignorés et signalés), `follow` (suivis, boucles détectées)
+    /// ou `record` (inscrits comme liens avec leur cible)
+    #[arg(long, value_enum, default_value_t = SymlinkPolicy::Skip)]
+    symlinks: SymlinkPolicy,
+    /// Ignore les fichiers et répertoires cachés
+    #[arg(long)]
+    skip_hidden: bool,
+    /// Ne descend pas dans les répertoires d'un autre système de fichiers que la source
+    #[arg(long)]
+    one_file_system: bool,
+    /// Ordre des lignes : `bytes` (octet par octet sur le chemin, comme `LC_ALL=C sort`) ou
+    /// `natural` (`fichier2` avant `fichier10`)
+    #[arg(long, value_enum, default_value_t = SortOrder::Bytes)]
+    sort: SortOrder,
+    /// Écrit les lignes dans l'ordre où les fichiers sont hachés, sans étape de tri
+    #[arg(long)]
+    no_sort: bool,
+    /// Journal des erreurs de lecture (défaut : <manifeste>.errors.log, écrit seulement en cas d'erreur)
+    #[arg(long)]
+    error_log: Option<PathBuf>,
+    /// Abandonne à la première erreur de lecture, sans toucher au manifeste existant
+    #[arg(long)]
+    strict: bool,
+    /// Conserve le manifeste précédent sous <manifeste>.bak
+    #[arg(long)]
+    backup: bool,
+    /// Reprend une exécution interrompue : les fichiers inscrits dans <manifeste>.journal
+    /// et inchangés depuis ne sont pas rehachés
+    #[arg(long)]
+    resume: bool,
 }
 
-fn main() -> std::io::Result<()> {
+/// Sort en erreur (code 1) si des fichiers n'ont pas pu être lus : ils sont
+/// signalés sur la sortie d'erreur et dans le journal, jamais dans le manifeste.
+fn main() -> io::Result<ExitCode> {
     let use_interactive = std::env::args().len() == 1; // aucun argument fourni
 
-    let args = if use_interactive {
+    let mut args = if use_interactive {
         // Menu interactif si aucun argument fourni
         get_interactive_args()?
     } else {
         Args::parse()
     };
+    if args.algo.is_empty() {
+        args.algo = match args.format {
+            ManifestFormat::Sfv => vec![HashAlgo::Crc32],
+            _ => vec![HashAlgo::Xxh3],
+        };
+    }
+    if args.format == ManifestFormat::Sfv && args.algo != [HashAlgo::Crc32] {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "le format SFV n'accepte que --algo crc32"));
+    }
 
     rayon::ThreadPoolBuilder::new().num_threads(args.threads).build_global().unwrap();
+    if let Err(e) = ctrlc::set_handler(|| INTERRUPTED.store(true, Ordering::SeqCst)) {
+        eprintln!("Ctrl-C ne pourra pas être intercepté : {}", e);
+    }
 
     fs::create_dir_all(&args.output_dir)?;
-    let output_file = args.output_dir.join(&args.name);
+    let name = args.name.as_deref().unwrap_or(Manifest::default_name(&args.algo, args.format));
+    let output_file = args.output_dir.join(name);
+    let stamps_file = Stamps::path_for(&output_file);
+    let error_log = args.error_log.clone().unwrap_or_else(|| error_log_path(&output_file));
+    let journal_file = with_suffix(&output_file, JOURNAL_SUFFIX);
+    let root = args.root.as_ref().unwrap_or(&args.output_dir);
 
-    let files: Vec<_> = WalkDir::new(&args.source)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().is_file())
-        .filter(|e| e.path() != output_file)
-        .map(|e| e.path().to_path_buf())
-        .collect();
+    let cache_file = args.output_dir.join(CACHE_FILE_NAME);
+    let use_cache = args.trust_cache || args.ignore_cache;
+    let cache = if args.trust_cache { HashCache::load(&cache_file)? } else { HashCache::default() };
 
-    let pb = ProgressBar::new(files.len() as u64);
+    let walk_options = WalkOptions {
+        include: args.include.clone(),
+        exclude: args.exclude.clone(),
+        gitignore: args.gitignore,
+        default_excludes: !args.no_default_excludes,
+        symlinks: args.symlinks,
+        skip_hidden: args.skip_hidden,
+        one_file_system: args.one_file_system,
+    };
+    // Ni nos propres fichiers (y compris les temporaires écrits pendant le parcours),
+    // ni l'exécutable s'il se trouve dans l'arborescence
+    let sort_dir = args.output_dir.join(format!(".zhashgen-sort-{}", std::process::id()));
+    let order_dir = args.output_dir.join(format!(".zhashgen-order-{}", std::process::id()));
+    let update_dir = args.output_dir.join(format!(".zhashgen-update-{}", std::process::id()));
+    let mut skip = vec![
+        output_file.clone(),
+        tmp_path(&output_file),
+        backup_path(&output_file),
+        stamps_file.clone(),
+        tmp_path(&stamps_file),
+        cache_file.clone(),
+        tmp_path(&cache_file),
+        error_log.clone(),
+        journal_file.clone(),
+        sort_dir.clone(),
+        order_dir.clone(),
+        update_dir.clone(),
+    ];
+    skip.extend(std::env::current_exe());
+
+    // Tenu même sans --resume, pour qu'une exécution interrompue puisse être reprise
+    let journal_header = format!("# zhashgen journal: {}", args.algo.iter().map(|a| a.name()).collect::<Vec<_>>().join(" "));
+    let (mut journal, records) = Journal::open(&journal_file, &journal_header, args.resume)?;
+    let journaled: HashMap<String, Recorded> = records.iter().filter_map(|record| parse_journal_record(record)).collect();
+
+    let pb = ProgressBar::new(0);
     pb.set_style(ProgressStyle::with_template("[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} {msg}")
         .unwrap()
         .progress_chars("##-"));
 
-    let start = Instant::now();
-    let results: Vec<_> = files.par_iter().map(|path| {
-        let res = match hash_file(path, args.full_load_limit, args.algo) {
-            Ok((digest, size)) => {
-                let rel = path.strip_prefix(&args.source).unwrap_or(path);
-                (format!("{digest} *..\\{}\n", rel.display()), size, 0)
+    let io = IoOptions { strategy: args.io, full_load_limit: args.full_load_limit, ..IoOptions::default() };
+    let devices = DeviceLimits::new(&args.io_threads)?;
+    let process = |path: &Path| -> io::Result<FileResult> {
+        let name = relative_name(root, path);
+        // Relevé avant la lecture : un fichier modifié pendant le hachage sera rehaché la fois suivante
+        let metadata = fs::metadata(path).ok();
+        let stamp = metadata.as_ref().and_then(|m| FileStamp::from_metadata(m).ok());
+        let key = metadata.as_ref().and_then(CacheKey::from_metadata);
+        let from_cache = || key.filter(|_| args.trust_cache).and_then(|key| cache.get_all(&key, &args.algo));
+        let from_journal = || match journaled.get(&name) {
+            Some(journaled) if Some(journaled.stamp) == stamp => Some(journaled.digests.clone()),
+            _ => None,
+        };
+        let res = if let Some(digests) = from_journal() {
+            FileResult::new(name, digests, stamp, key, 0, Origin::Journal, &args)
+        } else if let Some(digests) = from_cache() {
+            FileResult::new(name, digests, stamp, key, 0, Origin::Cache, &args)
+        } else {
+            let permit = metadata.as_ref().and_then(|metadata| devices.acquire(metadata));
+            match hash_file_multi(path, &args.algo, io.holding_permit(permit.is_some())) {
+                Ok((digests, size)) => {
+                    let digests = args.algo.iter().copied().zip(digests).collect();
+                    FileResult::new(name, digests, stamp, key, size, Origin::Hashed, &args)
+                }
+                Err(e) if args.strict => {
+                    return Err(io::Error::new(e.kind(), format!("{}: {}", path.display(), e)));
+                }
+                Err(e) => FileResult {
+                    line: String::new(),
+                    name,
+                    digests: Vec::new(),
+                    stamp: None,
+                    key: None,
+                    size: 0,
+                    origin: Origin::Hashed,
+                    error: Some(format!("{}: {}", path.display(), e)),
+                },
             }
-            Err(e) => (format!("[ERROR] {}: {}\n", path.display(), e), 0, 1),
         };
-        pb.inc(1);
-        res
-    }).collect();
+        Ok(res)
+    };
+
+    let start = Instant::now();
+    // Ordre indépendant du système de fichiers : deux arborescences identiques donnent le même manifeste
+    let order = (!args.no_sort).then_some(args.sort);
+    let header = Manifest::new(args.algo.clone()).header(args.format);
+    let mut writer = ManifestWriter::create(&output_file, &stamps_file, &sort_dir, order, header)?;
+    let mut stats = Stats::default();
+    // Réécrit au fil des résultats ; les empreintes des autres algorithmes y sont recopiées à la fin
+    let mut new_cache = if use_cache { Some(AtomicFile::create(&cache_file)?) } else { None };
+    if let Some(out) = &mut new_cache {
+        HashCache::write_header(out)?;
+    }
+
+    // Parcours, hachage et écriture en flux, reliés par des files bornées : la mémoire
+    // ne dépend pas du nombre de fichiers
+    let walked = thread::scope(|scope| {
+        let (path_tx, path_rx) = mpsc::sync_channel::<PathBuf>(QUEUE_LEN);
+        let (result_tx, result_rx) = mpsc::sync_channel::<FileResult>(QUEUE_LEN);
+        let (args, root, pb, skip, walk_options) = (&args, root, &pb, &skip, &walk_options);
+        let (output_file, stamps_file, order_dir, update_dir) = (&output_file, &stamps_file, &order_dir, &update_dir);
+        let journaled = &journaled;
+        let reused_tx = result_tx.clone();
+
+        let walker = scope.spawn(move || -> io::Result<Walked> {
+            let mut walked = Walked::default();
+            // Avec --update, les fichiers sont d'abord rapprochés par nom du manifeste précédent,
+            // par un tri externe comme le reste : la mémoire reste bornée
+            let mut previous = match args.update {
+                true => Some(sort_previous(update_dir, output_file, stamps_file, &args.algo)?),
+                false => None,
+            };
+            // Avec un ordre de lecture, les chemins ne partent qu'une fois tous connus et triés
+            let mut pending = match args.read_order {
+                ReadOrder::Listed => None,
+                _ => Some(ExternalSorter::new(order_dir, SortOrder::Bytes, DEFAULT_RUN_LEN)?),
+            };
+            let mut pending_error = None;
+            walk(&args.source, skip, walk_options, |item| {
+                match item {
+                    WalkItem::File(path) => {
+                        pb.inc_length(1);
+                        let pushed = match (&mut previous, &mut pending) {
+                            (Some(previous), _) => previous.push(file_record(relative_name(root, &path), &path)),
+                            (None, Some(pending)) => pending.push(position_record(&path, args.read_order)),
+                            (None, None) if INTERRUPTED.load(Ordering::Relaxed) || path_tx.send(path).is_err() => {
+                                return ControlFlow::Break(());
+                            }
+                            (None, None) => Ok(()),
+                        };
+                        if let Err(e) = pushed {
+                            pending_error = Some(e);
+                            return ControlFlow::Break(());
+                        }
+                    }
+                    WalkItem::Link(path, target) => walked.links.push(LinkEntry {
+                        path: relative_name(root, &path),
+                        target: target.to_string_lossy().into_owned(),
+                    }),
+                    WalkItem::Skipped(path, kind) => walked.skipped.push((path, kind)),
+                    WalkItem::Unmatched(glob) => walked.unmatched.push(glob),
+                    WalkItem::Error(error) => {
+                        walked.errors.push(error);
+                        if args.strict {
+                            return ControlFlow::Break(());
+                        }
+                    }
+                }
+                ControlFlow::Continue(())
+            })?;
+            if let Some(e) = pending_error {
+                return Err(e);
+            }
+            if args.strict && !walked.errors.is_empty() {
+                return Ok(walked);
+            }
+            if let Some(previous) = previous {
+                // Les fichiers inchangés depuis le manifeste précédent vont droit à l'écriture
+                let reused = |name: String, path: &Path, recorded: Recorded| -> Option<FileResult> {
+                    let metadata = fs::metadata(path).ok()?;
+                    let stamp = FileStamp::from_metadata(&metadata).ok()?;
+                    // Le journal a la priorité : le hachage l'y trouvera
+                    let resumable = journaled.get(&name).is_some_and(|journaled| journaled.stamp == stamp);
+                    if stamp != recorded.stamp || resumable {
+                        return None;
+                    }
+                    let key = CacheKey::from_metadata(&metadata);
+                    Some(FileResult::new(name, recorded.digests, Some(stamp), key, 0, Origin::Manifest, args))
+                };
+                let joined = join_previous(previous, &args.algo, |name, path, recorded| {
+                    if INTERRUPTED.load(Ordering::Relaxed) {
+                        return Err(io::ErrorKind::Interrupted.into());
+                    }
+                    if let Some(result) = recorded.and_then(|recorded| reused(name, &path, recorded)) {
+                        // Le destinataire ne disparaît que si l'écriture a échoué, signalé par ailleurs
+                        return reused_tx.send(result).map_err(|_| io::ErrorKind::Interrupted.into());
+                    }
+                    match &mut pending {
+                        Some(pending) => pending.push(position_record(&path, args.read_order)),
+                        None => path_tx.send(path).map_err(|_| io::ErrorKind::Interrupted.into()),
+                    }
+                });
+                match joined {
+                    Ok((listed, still_present)) => (walked.previous, walked.still_present) = (listed, still_present),
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => return Ok(walked),
+                    Err(e) => return Err(e),
+                }
+            }
+            if let Some(pending) = pending {
+                let sent = pending.finish(|record| {
+                    if INTERRUPTED.load(Ordering::Relaxed) || path_tx.send(record_path(&record)).is_err() {
+                        // Arrête la fusion ; l'interruption ou l'échec est signalé par ailleurs
+                        return Err(io::ErrorKind::Interrupted.into());
+                    }
+                    Ok(())
+                });
+                match sent {
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                    sent => sent?,
+                }
+            }
+            Ok(walked)
+        });
+        let hasher = scope.spawn(move || {
+            path_rx.into_iter().par_bridge().try_for_each_with(result_tx, |tx, path| {
+                if INTERRUPTED.load(Ordering::Relaxed) {
+                    return Err(interrupted());
+                }
+                let result = process(&path)?;
+                // Le destinataire ne disparaît que si l'écriture a échoué : inutile de continuer
+                tx.send(result).map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))
+            })
+        });
+
+        let written = result_rx.into_iter().try_for_each(|result| {
+            pb.inc(1);
+            stats.record(&result);
+            if let (Some(out), Some(key)) = (&mut new_cache, result.key) {
+                for (algo, digest) in &result.digests {
+                    HashCache::write_entry(out, &key, *algo, digest)?;
+                }
+            }
+            if result.error.is_none() && result.origin != Origin::Journal && let Some(stamp) = result.stamp {
+                journal.record(&journal_record(&result.name, stamp, &result.digests))?;
+            }
+            writer.push(&result)
+        });
+        let walked = walker.join().unwrap();
+        let hashed = hasher.join().unwrap();
+        // L'échec d'écriture, s'il y en a un, est la cause de celui du hachage
+        written?;
+        hashed?;
+        walked
+    })?;
     pb.finish();
+    if INTERRUPTED.load(Ordering::SeqCst) {
+        return Err(interrupted());
+    }
+    if let (true, Some(error)) = (args.strict, walked.errors.first()) {
+        return Err(io::Error::other(error.clone()));
+    }
 
-    let mut out = File::create(&output_file)?;
-    let (mut total_bytes, mut total_errors) = (0u64, 0u64);
-    for (line, size, err) in &results {
-        out.write_all(line.as_bytes())?;
-        total_bytes += *size;
-        total_errors += *err as u64;
+    let mut links = walked.links;
+    links.sort_by(|a, b| args.sort.compare(&a.path, &b.path));
+    writer.commit(&links, args.format, args.backup)?;
+    if let Some(mut out) = new_cache {
+        HashCache::copy_other_algos(&cache_file, &args.algo, &mut out)?;
+        out.commit(false)?;
+    }
+    journal.remove()?;
+
+    let errors: Vec<&String> = walked.errors.iter().chain(&stats.errors).collect();
+    if errors.is_empty() {
+        // Un journal resté d'une exécution précédente ne correspondrait plus au manifeste
+        if let Err(e) = fs::remove_file(&error_log) && e.kind() != io::ErrorKind::NotFound {
+            return Err(e);
+        }
+    } else {
+        let mut log = BufWriter::new(File::create(&error_log)?);
+        for error in &errors {
+            writeln!(log, "{}", error)?;
+        }
+        log.flush()?;
     }
 
     let elapsed = start.elapsed().as_secs_f64();
     println!("\nDone! Hashes saved to: {}", output_file.display());
     println!("=== Statistiques ===");
-    println!("Fichiers traités    : {}", files.len());
-    println!("Erreurs             : {}", total_errors);
-    println!("Volume total        : {}", human_readable(total_bytes));
+    println!("Fichiers traités    : {}", stats.files);
+    println!("Erreurs             : {}", errors.len());
+    if !links.is_empty() {
+        println!("Liens inscrits      : {}", links.len());
+    }
+    if !walked.skipped.is_empty() {
+        println!("Ignorés (spéciaux)  : {}", walked.skipped.len());
+    }
+    if args.update {
+        println!("Inchangés           : {}", stats.reused);
+        println!("Rehachés            : {}", stats.files - stats.reused);
+        println!("Supprimés           : {}", walked.previous - walked.still_present);
+    }
+    if args.trust_cache {
+        println!("Depuis le cache     : {}", stats.from_cache);
+    }
+    if args.resume {
+        println!("Repris du journal   : {}", stats.resumed);
+    }
+    println!("Volume total        : {}", human_readable(stats.total_bytes));
     println!("Temps écoulé        : {:.2} s", elapsed);
-    println!("Débit moyen         : {}/s", human_readable((total_bytes as f64 / elapsed) as u64));
+    println!("Débit moyen         : {}/s", human_readable((stats.total_bytes as f64 / elapsed) as u64));
+
+    if !walked.skipped.is_empty() {
+        println!("\n=== Entrées ignorées (ni fichier ni répertoire) ===");
+        for (path, kind) in &walked.skipped {
+            println!("  [{}] {}", kind.name(), path.display());
+        }
+    }
+    for glob in &walked.unmatched {
+        eprintln!("Attention : le motif --include `{}` ne correspond à aucun fichier", glob);
+    }
+    if !errors.is_empty() {
+        eprintln!("\n=== Erreurs (fichiers absents du manifeste, voir {}) ===", error_log.display());
+        for error in &errors {
+            eprintln!("  {}", error);
+        }
+    }
 
-    println!("Appuyez sur Entrée pour quitter...");
-    let mut pause = String::new();
-    io::stdin().read_line(&mut pause).unwrap();
+    // Seulement pour qui a lancé l'outil d'un double-clic : les scripts et cron n'ont personne pour appuyer
+    if use_interactive {
+        println!("Appuyez sur Entrée pour quitter...");
+        let mut pause = String::new();
+        io::stdin().read_line(&mut pause).unwrap();
+    }
 
-    Ok(())
+    Ok(if errors.is_empty() { ExitCode::SUCCESS } else { ExitCode::FAILURE })
 }
 
 fn get_interactive_args() -> io::Result<Args> {
@@ -106,65 +485,364 @@ fn get_interactive_args() -> io::Result<Args> {
     println!("  1. CRC32");
     println!("  2. MD5");
     println!("  3. XXH3 (défaut)");
-    print!("Votre choix [1-3] : ");
+    println!("  4. XXH3-128");
+    println!("  5. SHA-256");
+    println!("  6. SHA-512");
+    println!("  7. SHA-1 (compatibilité)");
+    println!("  8. BLAKE3");
+    println!("  9. CRC32 au format SFV");
+    println!(" 10. XXH3 arborescent (très gros fichiers, hachés en parallèle)");
+    print!("Votre choix [1-10] : ");
     io::stdout().flush()?;
     
     let mut choice_input = String::new();
     io::stdin().read_line(&mut choice_input)?;
-    let (algo, filename) = match choice_input.trim() {
-        "1" => (HashAlgo::Crc32, "CRC.crc32"),
-        "2" => (HashAlgo::Md5, "CRC.md5"),
-        _ => (HashAlgo::Xxh3, "CRC.xxhash3"),
+    let (algo, format) = match choice_input.trim() {
+        "1" => (HashAlgo::Crc32, ManifestFormat::Gnu),
+        "2" => (HashAlgo::Md5, ManifestFormat::Gnu),
+        "4" => (HashAlgo::Xxh128, ManifestFormat::Gnu),
+        "5" => (HashAlgo::Sha256, ManifestFormat::Gnu),
+        "6" => (HashAlgo::Sha512, ManifestFormat::Gnu),
+        "7" => (HashAlgo::Sha1, ManifestFormat::Gnu),
+        "8" => (HashAlgo::Blake3, ManifestFormat::Gnu),
+        "9" => (HashAlgo::Crc32, ManifestFormat::Sfv),
+        "10" => (HashAlgo::Xxh3Tree, ManifestFormat::Gnu),
+        _ => (HashAlgo::Xxh3, ManifestFormat::Gnu),
     };
 
     Ok(Args {
         source: PathBuf::from("."),
         output_dir: PathBuf::from("./xxHash"),
-        name: filename.to_string(),
-        full_load_limit: u64::MAX, // Pas de limite, charge tout en mémoire
+        name: None,
+        root: None,
+        text: false,
+        format,
+        full_load_limit: DEFAULT_FULL_LOAD_LIMIT,
+        io: IoStrategy::Auto,
+        io_threads: Vec::new(),
+        read_order: ReadOrder::Listed,
         threads: num_cpus::get(),
-        algo,
+        algo: vec![algo],
+        update: false,
+        trust_cache: false,
+        ignore_cache: false,
+        include: Vec::new(),
+        exclude: Vec::new(),
+        gitignore: false,
+        no_default_excludes: false,
+        symlinks: SymlinkPolicy::Skip,
+        skip_hidden: false,
+        one_file_system: false,
+        sort: SortOrder::Bytes,
+        no_sort: false,
+        error_log: None,
+        strict: false,
+        backup: false,
+        resume: false,
     })
 }
 
-fn hash_file(path: &Path, full_load_limit: u64, algo: HashAlgo) -> io::Result<(String, u64)> {
-    let meta = fs::metadata(path)?;
-    let size = meta.len();
-    let mut file = File::open(path)?;
-    
-    if size <= full_load_limit {
-        let mut buf = Vec::with_capacity(size as usize);
-        file.read_to_end(&mut buf)?;
-        let digest = match algo {
-            HashAlgo::Crc32 => format!("{:08x}", crc32fast::hash(&buf)),
-            HashAlgo::Md5 => format!("{:x}", md5::compute(&buf)),
-            HashAlgo::Xxh3 => format!("{:016x}", xxhash_rust::xxh3::xxh3_64(&buf)),
+/// Chemins en attente de hachage, et résultats en attente d'écriture.
+const QUEUE_LEN: usize = 1024;
+
+/// Levé par Ctrl-C : le parcours et le hachage s'arrêtent, les fichiers
+/// temporaires sont supprimés et le manifeste précédent reste intact.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+fn interrupted() -> io::Error {
+    io::Error::new(io::ErrorKind::Interrupted, "interrompu, manifeste précédent conservé")
+}
+
+/// Provenance des empreintes d'un fichier.
+#[derive(Clone, Copy, PartialEq)]
+enum Origin {
+    Hashed,
+    /// Reprises du manifeste précédent (`--update`)
+    Manifest,
+    /// Reprises du cache (`--trust-cache`)
+    Cache,
+    /// Reprises du journal d'une exécution interrompue (`--resume`)
+    Journal,
+}
+
+/// Résultat du traitement d'un fichier : la ligne à écrire dans le manifeste
+/// et de quoi alimenter les statistiques, le fichier de relevés et le cache.
+struct FileResult {
+    line: String,
+    name: String,
+    digests: Vec<(HashAlgo, String)>,
+    stamp: Option<FileStamp>,
+    key: Option<CacheKey>,
+    /// Octets lus (0 si les empreintes n'ont pas été recalculées)
+    size: u64,
+    origin: Origin,
+    /// Erreur de lecture ; la ligne est alors vide et le fichier absent du manifeste
+    error: Option<String>,
+}
+
+impl FileResult {
+    fn new(
+        name: String,
+        digests: Vec<(HashAlgo, String)>,
+        stamp: Option<FileStamp>,
+        key: Option<CacheKey>,
+        size: u64,
+        origin: Origin,
+        args: &Args,
+    ) -> Self {
+        let mut entry = ManifestEntry::new(name, digests);
+        entry.binary = !args.text;
+        // Seul le format SFV peut refuser un nom : le fichier est alors signalé comme une erreur
+        let (line, error) = match entry.format(args.format) {
+            Ok(line) => (line, None),
+            Err(e) => (String::new(), Some(e.to_string())),
         };
-        Ok((digest, size))
-    } else {
-        // Pour les gros fichiers, hash par chunks
-        let mut hasher_crc32 = crc32fast::Hasher::new();
-        let mut hasher_md5 = md5::Context::new();
-        let mut hasher_xxh3 = xxhash_rust::xxh3::Xxh3::new();
-        let mut buf = [0u8; 1024 * 1024];
-        
-        loop {
-            let n = file.read(&mut buf)?;
-            if n == 0 { break; }
-            match algo {
-                HashAlgo::Crc32 => { hasher_crc32.update(&buf[..n]); }
-                HashAlgo::Md5 => { hasher_md5.consume(&buf[..n]); }
-                HashAlgo::Xxh3 => { hasher_xxh3.update(&buf[..n]); }
-            }
+        Self { line, name: entry.path, digests: entry.digests, stamp, key, size, origin, error }
+    }
+}
+
+/// Ce que le parcours a trouvé en dehors des fichiers à hacher.
+#[derive(Default)]
+struct Walked {
+    links: Vec<LinkEntry>,
+    skipped: Vec<(PathBuf, SpecialKind)>,
+    errors: Vec<String>,
+    /// Motifs `--include` qui n'ont retenu aucun fichier
+    unmatched: Vec<String>,
+    /// Fichiers du manifeste précédent (`--update`)
+    previous: usize,
+    /// Fichiers du manifeste précédent toujours présents
+    still_present: usize,
+}
+
+/// Statistiques cumulées au fil des résultats.
+#[derive(Default)]
+struct Stats {
+    files: usize,
+    total_bytes: u64,
+    /// Empreintes reprises du manifeste précédent
+    reused: usize,
+    from_cache: usize,
+    resumed: usize,
+    errors: Vec<String>,
+}
+
+impl Stats {
+    fn record(&mut self, result: &FileResult) {
+        self.files += 1;
+        self.total_bytes += result.size;
+        match result.origin {
+            Origin::Manifest => self.reused += 1,
+            Origin::Cache => self.from_cache += 1,
+            Origin::Journal => self.resumed += 1,
+            Origin::Hashed => {}
         }
-        
-        let digest = match algo {
-            HashAlgo::Crc32 => format!("{:08x}", hasher_crc32.finalize()),
-            HashAlgo::Md5 => format!("{:x}", hasher_md5.finalize()),
-            HashAlgo::Xxh3 => format!("{:016x}", hasher_xxh3.digest()),
+        if let Some(error) = &result.error {
+            self.errors.push(error.clone());
+        }
+    }
+}
+
+/// Écrit le manifeste et ses relevés au fil de l'eau dans des fichiers
+/// temporaires, mis en place par [`ManifestWriter::commit`] ; abandonné, il
+/// les supprime. Avec un ordre de tri, les lignes passent par un tri externe.
+struct ManifestWriter {
+    manifest: AtomicFile,
+    stamps: AtomicFile,
+    sorter: Option<ExternalSorter>,
+}
+
+impl ManifestWriter {
+    fn create(
+        manifest: &Path,
+        stamps: &Path,
+        sort_dir: &Path,
+        order: Option<SortOrder>,
+        header: Option<String>,
+    ) -> io::Result<Self> {
+        let sorter = match order {
+            Some(order) => Some(ExternalSorter::new(sort_dir, order, DEFAULT_RUN_LEN)?),
+            None => None,
+        };
+        let mut writer = Self {
+            manifest: AtomicFile::create(manifest)?,
+            stamps: AtomicFile::create(stamps)?,
+            sorter,
         };
-        Ok((digest, size))
+        if let Some(header) = header {
+            writer.manifest.write_all(header.as_bytes())?;
+        }
+        Ok(writer)
+    }
+
+    /// Ajoute un fichier ; ceux en erreur n'apparaissent pas dans le manifeste.
+    fn push(&mut self, result: &FileResult) -> io::Result<()> {
+        if result.error.is_some() {
+            return Ok(());
+        }
+        let stamp_line = result.stamp.map(|stamp| Stamps::format_line(&result.name, stamp)).unwrap_or_default();
+        match &mut self.sorter {
+            Some(sorter) => sorter.push(vec![result.name.clone(), result.line.clone(), stamp_line]),
+            None => {
+                self.manifest.write_all(result.line.as_bytes())?;
+                self.stamps.write_all(stamp_line.as_bytes())
+            }
+        }
+    }
+
+    /// Termine l'écriture (fusion des lignes triées, puis les liens) et met
+    /// les fichiers en place, en gardant si demandé l'ancien manifeste en `.bak`.
+    fn commit(mut self, links: &[LinkEntry], format: ManifestFormat, backup: bool) -> io::Result<()> {
+        if let Some(sorter) = self.sorter.take() {
+            let (manifest, stamps) = (&mut self.manifest, &mut self.stamps);
+            sorter.finish(|record| {
+                manifest.write_all(record[1].as_bytes())?;
+                stamps.write_all(record[2].as_bytes())
+            })?;
+        }
+        for link in links {
+            self.manifest.write_all(link.format(format).as_bytes())?;
+        }
+        self.manifest.commit(backup)?;
+        self.stamps.commit(false)
+    }
+}
+
+/// Ligne du journal : chemin, taille et date de modification au moment du
+/// hachage, puis une empreinte `algo=hex` par algorithme.
+fn journal_record(name: &str, stamp: FileStamp, digests: &[(HashAlgo, String)]) -> Vec<String> {
+    let mut record = vec![name.to_string(), stamp.size.to_string(), stamp.mtime_ns.to_string()];
+    record.extend(digest_fields(digests));
+    record
+}
+
+/// Empreintes relevées pour un fichier, reprises telles quelles s'il n'a pas
+/// changé depuis : celles du journal ou du manifeste précédent.
+struct Recorded {
+    stamp: FileStamp,
+    digests: Vec<(HashAlgo, String)>,
+}
+
+fn parse_journal_record(record: &[String]) -> Option<(String, Recorded)> {
+    let [name, size, mtime_ns, digests @ ..] = record else {
+        return None;
+    };
+    let stamp = parse_stamp(size, mtime_ns)?;
+    Some((name.clone(), Recorded { stamp, digests: parse_digest_fields(digests)? }))
+}
+
+/// Un champ `algo=hex` par empreinte.
+fn digest_fields(digests: &[(HashAlgo, String)]) -> impl Iterator<Item = String> + '_ {
+    digests.iter().map(|(algo, digest)| format!("{}={}", algo.name(), digest))
+}
+
+fn parse_digest_fields(fields: &[String]) -> Option<Vec<(HashAlgo, String)>> {
+    fields
+        .iter()
+        .map(|field| {
+            let (algo, digest) = field.split_once('=')?;
+            Some((HashAlgo::from_name(algo)?, digest.to_string()))
+        })
+        .collect()
+}
+
+fn parse_stamp(size: &str, mtime_ns: &str) -> Option<FileStamp> {
+    Some(FileStamp { size: size.parse().ok()?, mtime_ns: mtime_ns.parse().ok()? })
+}
+
+/// Marques des enregistrements du tri de `--update`, en deuxième champ après
+/// le nom : fichier parcouru, entrée du manifeste précédent, relevé.
+const WALKED_FILE: &str = "F";
+const PREVIOUS_ENTRY: &str = "M";
+const PREVIOUS_STAMP: &str = "S";
+
+/// Trie par nom les entrées du manifeste existant et leurs relevés, pour
+/// `--update` ; les fichiers parcourus s'y ajoutent avec [`file_record`],
+/// puis [`join_previous`] les rapproche. Un manifeste absent revient à tout
+/// hacher.
+fn sort_previous(dir: &Path, manifest: &Path, stamps: &Path, algos: &[HashAlgo]) -> io::Result<ExternalSorter> {
+    let mut sorter = ExternalSorter::new(dir, SortOrder::Bytes, DEFAULT_RUN_LEN)?;
+    if !manifest.exists() {
+        return Ok(sorter);
+    }
+    let algo = match algos {
+        [algo] => Some(*algo),
+        _ => None,
+    };
+    Manifest::read_entries(manifest, algo, |entry| {
+        let mut record = vec![entry.path, PREVIOUS_ENTRY.to_string()];
+        record.extend(digest_fields(&entry.digests));
+        sorter.push(record)
+    })?;
+    Stamps::read(stamps, |name, stamp| {
+        sorter.push(vec![name, PREVIOUS_STAMP.to_string(), stamp.size.to_string(), stamp.mtime_ns.to_string()])
+    })?;
+    Ok(sorter)
+}
+
+/// Enregistrement d'un fichier parcouru, pour [`sort_previous`].
+fn file_record(name: String, path: &Path) -> Vec<String> {
+    let mut record = vec![name];
+    record.extend(path_record(WALKED_FILE.to_string(), path));
+    record
+}
+
+/// Ce que le tri de `--update` réunit sous un même nom.
+#[derive(Default)]
+struct Joined {
+    name: String,
+    paths: Vec<PathBuf>,
+    listed: bool,
+    stamp: Option<FileStamp>,
+    digests: Vec<(HashAlgo, String)>,
+}
+
+/// Passe à `visit`, dans l'ordre des noms, chaque fichier parcouru avec son
+/// relevé et ses empreintes du manifeste précédent, si elles couvrent tous
+/// les `algos` demandés. Renvoie le nombre de fichiers du manifeste précédent
+/// et celui de ceux qui sont toujours là.
+fn join_previous(
+    sorter: ExternalSorter,
+    algos: &[HashAlgo],
+    mut visit: impl FnMut(String, PathBuf, Option<Recorded>) -> io::Result<()>,
+) -> io::Result<(usize, usize)> {
+    let (mut listed, mut still_present) = (0, 0);
+    let mut flush = |joined: Joined| -> io::Result<()> {
+        if joined.listed {
+            listed += 1;
+            still_present += usize::from(!joined.paths.is_empty());
+        }
+        let digests: Option<Vec<_>> = algos
+            .iter()
+            .map(|algo| joined.digests.iter().find(|(recorded, _)| recorded == algo).cloned())
+            .collect();
+        for path in joined.paths {
+            let recorded = joined.stamp.zip(digests.clone()).map(|(stamp, digests)| Recorded { stamp, digests });
+            visit(joined.name.clone(), path, recorded)?;
+        }
+        Ok(())
+    };
+    let mut current: Option<Joined> = None;
+    sorter.finish(|record| {
+        if let Some(joined) = current.take_if(|joined| joined.name != record[0]) {
+            flush(joined)?;
+        }
+        let joined = current.get_or_insert_with(|| Joined { name: record[0].clone(), ..Joined::default() });
+        match record.get(1).map(String::as_str) {
+            Some(WALKED_FILE) => joined.paths.push(record_path(&record[1..])),
+            Some(PREVIOUS_ENTRY) => {
+                joined.listed = true;
+                joined.digests.extend(parse_digest_fields(&record[2..]).unwrap_or_default());
+            }
+            Some(PREVIOUS_STAMP) => joined.stamp = record.get(2..4).and_then(|fields| parse_stamp(&fields[0], &fields[1])),
+            _ => {}
+        }
+        Ok(())
+    })?;
+    if let Some(joined) = current {
+        flush(joined)?;
     }
+    Ok((listed, still_present))
 }
 
 fn human_readable(num_bytes: u64) -> String {
@@ -1,511 +1,349 @@
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser, ValueEnum};
 use std::fs::File;
-use std::io::{BufReader, Read, Write, stdin};
+use std::io;
 use std::path::{Path, PathBuf};
-use std::sync::Mutex;
-use rayon::prelude::*;
-use xxhash_rust::xxh3::Xxh3;
-use std::time::Instant;
-use md5;
-use crc32fast;
-
-#[derive(Debug, Clone, Copy, PartialEq)]
-enum HashType {
-    Xxh3,
-    Md5,
-    Crc32,
+use std::process::ExitCode;
+use std::time::Duration;
+use zhash_core::atomic::backup_path;
+use zhash_core::cache::CACHE_FILE_NAME;
+use zhash_core::console::{self, ConsoleObserver};
+use zhash_core::device::{DeviceLimits, IoThreads};
+use zhash_core::order::ReadOrder;
+use zhash_core::report::ManifestReport;
+use zhash_core::sidecar::{VERIFY_JOURNAL_SUFFIX, error_log_path, with_suffix};
+use zhash_core::stamp::Stamps;
+use zhash_core::throttle::{Throttle, lower_priority, parse_rate};
+use zhash_core::walk::{SymlinkPolicy, WalkOptions};
+use zhash_core::{
+    DEFAULT_FULL_LOAD_LIMIT, HashAlgo, IoOptions, IoStrategy, Manifest, Report, Verifier, VerifyExit, VerifyObserver,
+};
+
+/// Verifies files against CRC.xxhash3 / CRC.xxh128 / CRC.md5 / CRC.crc32 /
+/// CRC.sha1 / CRC.sha256 / CRC.sha512 / CRC.blake3 manifests.
+///
+/// Without arguments, looks for a manifest in the current directory, then
+/// next to the executable.
+#[derive(Parser)]
+#[command(version, after_help = VerifyExit::HELP)]
+struct Args {
+    /// Manifest file(s) to verify, one after the other
+    manifests: Vec<PathBuf>,
+    /// Directory manifest entries are resolved against (default: the manifest's directory)
+    #[arg(short, long)]
+    base_dir: Option<PathBuf>,
+    /// Hash algorithm, instead of detecting it from the manifest
+    #[arg(short, long, value_enum)]
+    algo: Option<HashAlgo>,
+    /// Only check these digests of a multi-algorithm manifest, comma-separated (default: all of them)
+    #[arg(short, long, value_enum, value_delimiter = ',')]
+    check: Vec<HashAlgo>,
+    /// Number of files hashed in parallel (default: one per CPU)
+    #[arg(long)]
+    threads: Option<usize>,
+    /// Exit right away instead of waiting for Enter
+    #[arg(long)]
+    no_pause: bool,
+    /// Only print the final results, without per-file progress
+    #[arg(short, long)]
+    quiet: bool,
+    /// Report format; `json` prints a single JSON document on stdout and nothing else
+    #[arg(long, value_enum, default_value_t = ReportFormat::Text)]
+    report: ReportFormat,
+    /// Write the JSON report to this file, keeping the normal console output (implies --report json)
+    #[arg(long)]
+    report_file: Option<PathBuf>,
+    /// Also list files under the base directory that the manifest does not mention
+    #[arg(long)]
+    untracked: bool,
+    /// With --untracked, only look at files matching this glob (`.gitignore` syntax, repeatable)
+    #[arg(long, requires = "untracked")]
+    include: Vec<String>,
+    /// With --untracked, skip files and directories matching this glob (`.gitignore` syntax,
+    /// repeatable)
+    #[arg(long, requires = "untracked")]
+    exclude: Vec<String>,
+    /// With --untracked, also honor `.gitignore` files (`.zhashignore` ones always are)
+    #[arg(long, requires = "untracked")]
+    gitignore: bool,
+    /// With --untracked, do not skip `.git`, `Thumbs.db`, `.DS_Store`, etc. by default
+    #[arg(long, requires = "untracked")]
+    no_default_excludes: bool,
+    /// With --untracked, follow symbolic links instead of leaving them out
+    #[arg(long, requires = "untracked")]
+    follow_symlinks: bool,
+    /// With --untracked, skip hidden files and directories
+    #[arg(long, requires = "untracked")]
+    skip_hidden: bool,
+    /// With --untracked, do not descend into directories on another filesystem
+    #[arg(long, requires = "untracked")]
+    one_file_system: bool,
+    /// How files are read: `auto` (loaded whole up to --full-load-limit, streamed above),
+    /// `read` (1 MiB blocks), `mmap` (memory-mapped on read-only mounts, read in
+    /// blocks elsewhere) or `whole` (loaded whole)
+    #[arg(long, value_enum, default_value_t = IoStrategy::Auto)]
+    io: IoStrategy,
+    /// Largest file, in bytes, that `--io auto` loads whole
+    #[arg(long, default_value_t = DEFAULT_FULL_LOAD_LIMIT)]
+    full_load_limit: u64,
+    /// Files read at once from a device: `N` for every device, `PATH=N` for the one holding
+    /// PATH, 0 for no limit; repeatable (default: 1 on spinning and USB disks, else no limit)
+    #[arg(long, value_name = "[PATH=]N")]
+    io_threads: Vec<IoThreads>,
+    /// Order files are read in: `listed` (manifest order), `inode`, or `physical` (first extent
+    /// on disk, Linux only); the last two speed up cold reads from spinning disks
+    #[arg(long, value_enum, default_value_t = ReadOrder::Listed)]
+    read_order: ReadOrder,
+    /// Read at most this many bytes per second across all threads, e.g. `50M` (K, M, G: powers of 1024)
+    #[arg(long, value_name = "RATE", value_parser = parse_rate)]
+    max_rate: Option<u64>,
+    /// Pause while the 1-minute load average exceeds this (Linux only; count this run's own threads)
+    #[arg(long, value_name = "LOAD")]
+    max_load: Option<f64>,
+    /// Run at this CPU niceness, 0 to 19; I/O priority follows (Linux only)
+    #[arg(long, value_parser = clap::value_parser!(i32).range(0..=19))]
+    nice: Option<i32>,
+    /// Only read when no other process uses the disk (idle I/O class, Linux only)
+    #[arg(long)]
+    idle_io: bool,
+    /// Keep a journal next to the manifest so that an interrupted run can be continued with --resume
+    #[arg(long)]
+    journal: bool,
+    /// Continue an interrupted verification of the same manifest instead of starting over
+    /// (implies --journal)
+    #[arg(long)]
+    resume: bool,
 }
 
-#[derive(Debug, Clone, PartialEq)]
-enum FileStatus {
-    Ok,
-    Corrupted,
-    Missing,
-    Error,
+#[derive(Copy, Clone, PartialEq, ValueEnum)]
+enum ReportFormat {
+    Text,
+    Json,
 }
 
-impl FileStatus {
-    fn symbol(&self) -> &str {
-        match self {
-            FileStatus::Ok => "✓",
-            FileStatus::Corrupted => "✗",
-            FileStatus::Missing => "?",
-            FileStatus::Error => "!",
-        }
+impl Args {
+    /// Whether human-readable output goes to stdout (it doesn't when stdout carries the JSON report).
+    fn console(&self) -> bool {
+        self.report == ReportFormat::Text || self.report_file.is_some()
     }
 
-    fn text(&self) -> &str {
-        match self {
-            FileStatus::Ok => "OK",
-            FileStatus::Corrupted => "CORRUPTED",
-            FileStatus::Missing => "MISSING",
-            FileStatus::Error => "ERROR",
-        }
+    /// Whether a JSON report is written at all, on stdout or to a file.
+    fn wants_report(&self) -> bool {
+        self.report == ReportFormat::Json || self.report_file.is_some()
     }
 
-    fn color(&self) -> &str {
-        match self {
-            FileStatus::Ok => "\x1b[32m",      // Green
-            FileStatus::Corrupted | FileStatus::Error => "\x1b[31m", // Red
-            FileStatus::Missing => "\x1b[33m",   // Yellow
+    /// Which files --untracked looks at.
+    fn walk_options(&self) -> WalkOptions {
+        WalkOptions {
+            include: self.include.clone(),
+            exclude: self.exclude.clone(),
+            gitignore: self.gitignore,
+            default_excludes: !self.no_default_excludes,
+            symlinks: if self.follow_symlinks { SymlinkPolicy::Follow } else { SymlinkPolicy::Skip },
+            skip_hidden: self.skip_hidden,
+            one_file_system: self.one_file_system,
         }
     }
 }
 
-#[derive(Debug, Clone)]
-struct FileCheck {
-    path: String,
-    expected_hash: String,
-    status: Option<FileStatus>,
-}
-
-struct VerificationResult {
-    file_check: FileCheck,
-    status: FileStatus,
-}
-
 struct Xxh3VerifierCli {
-    base_path: PathBuf,
-    files: Vec<FileCheck>,
-    hash_type: HashType,
+    verifier: Verifier,
+    args: Args,
+    report: Report,
+    manifest_failed: bool,
+    /// Part of a tree could not be walked for --untracked, which counts as a read error.
+    untracked_failed: bool,
 }
 
 impl Xxh3VerifierCli {
-    fn new() -> Self {
+    fn new(args: Args, devices: DeviceLimits) -> Self {
+        let mut verifier = Verifier::new();
+        verifier.io = IoOptions { strategy: args.io, full_load_limit: args.full_load_limit, ..IoOptions::default() };
+        verifier.devices = devices;
+        verifier.read_order = args.read_order;
+        verifier.throttle = Throttle::new(args.max_rate, args.max_load);
         Self {
-            base_path: PathBuf::new(),
-            files: Vec::new(),
-            hash_type: HashType::Xxh3,
+            verifier,
+            args,
+            report: Report::default(),
+            manifest_failed: false,
+            untracked_failed: false,
         }
     }
 
-    fn auto_load_hash_file(&mut self) -> Result<(), String> {
-        let current_dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
-        let candidates = [
-            ("CRC.xxhash3", HashType::Xxh3),
-            ("CRC.md5", HashType::Md5),
-            ("CRC.crc32", HashType::Crc32),
-        ];
-
-        for (fname, htype) in candidates.iter() {
-            let hash_file_path = current_dir.join(fname);
-            if hash_file_path.exists() {
-                self.hash_type = *htype;
-                return self.load_hash_file(&hash_file_path);
+    fn verify_files(&mut self, manifest: &Path) -> Duration {
+        let total_files = self.verifier.files.len();
+        let quiet = self.args.quiet || !self.args.console();
+        console::timed(total_files, self.args.console(), || {
+            if quiet {
+                self.verify_journaled(manifest, &());
+            } else {
+                self.verify_journaled(manifest, &ConsoleObserver::new(total_files));
             }
-        }
+        })
+    }
 
-        if let Ok(exe_path) = std::env::current_exe() {
-            if let Some(exe_dir) = exe_path.parent() {
-                for (fname, htype) in candidates.iter() {
-                    let hash_file_path = exe_dir.join(fname);
-                    if hash_file_path.exists() {
-                        self.hash_type = *htype;
-                        return self.load_hash_file(&hash_file_path);
-                    }
-                }
-            }
+    /// With --journal or --resume, verifies with a journal next to the manifest, so that an
+    /// interrupted run can be resumed; without one if it cannot be written, e.g. on a
+    /// read-only share. Otherwise nothing is written into the verified tree.
+    fn verify_journaled(&mut self, manifest: &Path, observer: &impl VerifyObserver) {
+        if !self.args.journal && !self.args.resume {
+            self.verifier.verify_files(observer);
+            return;
+        }
+        let journal = with_suffix(manifest, VERIFY_JOURNAL_SUFFIX);
+        if let Err(e) = self.verifier.verify_files_journaled(observer, &journal, self.args.resume) {
+            eprintln!("Cannot use journal {}: {e}; verifying from the start", journal.display());
+            self.verifier.verify_files(observer);
         }
-
-        Err("No CRC.xxhash3, CRC.md5 or CRC.crc32 file found".to_string())
     }
 
-    fn load_hash_file(&mut self, path: &Path) -> Result<(), String> {
-        let file = File::open(path).map_err(|e| format!("Error opening file: {}", e))?;
-        let mut reader = BufReader::new(file);
-        let mut buffer = Vec::new();
-
-        reader.read_to_end(&mut buffer).map_err(|e| format!("Error reading file: {}", e))?;
-
-        let content = String::from_utf8_lossy(&buffer);
-
-        self.base_path = path.parent().unwrap().to_path_buf();
-        self.files.clear();
-
-        for line in content.lines() {
-            let line = line.trim();
-            if line.is_empty() {
-                continue;
-            }
+    fn run(&mut self) {
+        let console = self.args.console();
+        if console {
+            console::print_title();
+        }
 
-            let (hash, file_path) = match self.hash_type {
-                HashType::Xxh3 | HashType::Md5 => {
-                    let parts: Vec<&str> = line.splitn(2, ' ').collect();
-                    if parts.len() != 2 {
-                        continue;
-                    }
-                    (parts[0].to_string(), parts[1].trim_start_matches('*').to_string())
-                }
-                HashType::Crc32 => {
-                    // Format: CRC32 (hex) [space] filename
-                    let parts: Vec<&str> = line.splitn(2, ' ').collect();
-                    if parts.len() != 2 {
-                        continue;
+        if self.args.manifests.is_empty() {
+            let algos = match self.args.algo {
+                Some(algo) => vec![algo],
+                None => HashAlgo::ALL.to_vec(),
+            };
+            match self.verifier.auto_load_hash_file(&Manifest::default_search_dirs(), &algos) {
+                Ok(path) => self.check_loaded(&path),
+                Err(e) => {
+                    self.manifest_failed = true;
+                    self.report.push(ManifestReport::failed(Path::new(algos[0].manifest_name()), &e));
+                    if console {
+                        console::print_not_found(&e, &algos);
                     }
-                    (parts[0].to_lowercase(), parts[1].trim_start_matches('*').to_string())
                 }
-            };
-
-            self.files.push(FileCheck {
-                path: file_path,
-                expected_hash: hash,
-                status: None,
-            });
+            }
+            return;
         }
 
-        Ok(())
-    }
-    
-    fn verify_files(&mut self) {
-        let total_files = self.files.len();
-        let start_time = Instant::now();
-        const BAR_WIDTH: usize = 40;
-
-        println!("\n\x1b[1m🔍 Starting verification of {} files...\x1b[0m", total_files);
-
-        let display_mutex = Mutex::new(());
-
-        let files_to_process: Vec<FileCheck> = self.files.clone();
-        let hash_type = self.hash_type;
-
-        let results: Vec<_> = files_to_process
-            .par_iter()
-            .enumerate()
-            .map(|(index, file_check)| {
-                let file_number = index + 1;
-                let file_path = PathBuf::from(&file_check.path);
-                let full_path = if file_path.is_absolute() {
-                    file_path
-                } else {
-                    self.base_path.join(&file_path)
-                };
-
-                let mut last_percent = 0.0;
-                let mut progress_bar = String::new();
-                let mut percent = 0.0;
-                let mut header = String::new();
-
-                let status = if !full_path.exists() {
-                    FileStatus::Missing
-                } else {
-                    let hash_result = match hash_type {
-                        HashType::Xxh3 => calculate_xxh3_hash_with_progress(&full_path, |read, total| {
-                            percent = if total > 0 {
-                                (read as f64 / total as f64) * 100.0
-                            } else {
-                                100.0
-                            };
-                            if total > 0 && (percent - last_percent > 0.5 || percent == 100.0) {
-                                last_percent = percent;
-                                let filled = ((percent / 100.0) * BAR_WIDTH as f64).round() as usize;
-                                let empty = BAR_WIDTH - filled;
-                                progress_bar = format!(
-                                    "{}{}",
-                                    "\x1b[44m \x1b[0m".repeat(filled),
-                                    "\x1b[100m \x1b[0m".repeat(empty)
-                                );
-                                header = format!(
-                                    "\r\x1b[1;34m[{:>3}/{:<3}]\x1b[0m \x1b[36m{:<40}\x1b[0m",
-                                    file_number, total_files, file_check.path
-                                );
-                                print!(
-                                    "{} [{}] {:>6.1}%\x1b[K",
-                                    header, progress_bar, percent
-                                );
-                                std::io::stdout().flush().unwrap();
-                            }
-                        }),
-                        HashType::Md5 => calculate_md5_hash_with_progress(&full_path, |read, total| {
-                            percent = if total > 0 {
-                                (read as f64 / total as f64) * 100.0
-                            } else {
-                                100.0
-                            };
-                            if total > 0 && (percent - last_percent > 0.5 || percent == 100.0) {
-                                last_percent = percent;
-                                let filled = ((percent / 100.0) * BAR_WIDTH as f64).round() as usize;
-                                let empty = BAR_WIDTH - filled;
-                                progress_bar = format!(
-                                    "{}{}",
-                                    "\x1b[44m \x1b[0m".repeat(filled),
-                                    "\x1b[100m \x1b[0m".repeat(empty)
-                                );
-                                header = format!(
-                                    "\r\x1b[1;34m[{:>3}/{:<3}]\x1b[0m \x1b[36m{:<40}\x1b[0m",
-                                    file_number, total_files, file_check.path
-                                );
-                                print!(
-                                    "{} [{}] {:>6.1}%\x1b[K",
-                                    header, progress_bar, percent
-                                );
-                                std::io::stdout().flush().unwrap();
-                            }
-                        }),
-                        HashType::Crc32 => calculate_crc32_hash_with_progress(&full_path, |read, total| {
-                            percent = if total > 0 {
-                                (read as f64 / total as f64) * 100.0
-                            } else {
-                                100.0
-                            };
-                            if total > 0 && (percent - last_percent > 0.5 || percent == 100.0) {
-                                last_percent = percent;
-                                let filled = ((percent / 100.0) * BAR_WIDTH as f64).round() as usize;
-                                let empty = BAR_WIDTH - filled;
-                                progress_bar = format!(
-                                    "{}{}",
-                                    "\x1b[44m \x1b[0m".repeat(filled),
-                                    "\x1b[100m \x1b[0m".repeat(empty)
-                                );
-                                header = format!(
-                                    "\r\x1b[1;34m[{:>3}/{:<3}]\x1b[0m \x1b[36m{:<40}\x1b[0m",
-                                    file_number, total_files, file_check.path
-                                );
-                                print!(
-                                    "{} [{}] {:>6.1}%\x1b[K",
-                                    header, progress_bar, percent
-                                );
-                                std::io::stdout().flush().unwrap();
-                            }
-                        }),
-                    };
-                    match hash_result {
-                        Ok(calculated_hash) => {
-                            // Pour CRC32, on ignore la casse et les zéros non significatifs
-                            let expected = match hash_type {
-                                HashType::Crc32 => file_check.expected_hash.trim_start_matches("0x").trim_start_matches('0').to_lowercase(),
-                                _ => file_check.expected_hash.to_lowercase(),
-                            };
-                            let actual = match hash_type {
-                                HashType::Crc32 => calculated_hash.trim_start_matches("0x").trim_start_matches('0').to_lowercase(),
-                                _ => calculated_hash.to_lowercase(),
-                            };
-                            if actual == expected {
-                                FileStatus::Ok
-                            } else {
-                                FileStatus::Corrupted
-                            }
-                        }
-                        Err(_) => FileStatus::Error
+        for path in self.args.manifests.clone() {
+            match self.verifier.load_hash_file(&path, self.args.algo) {
+                Ok(()) => self.check_loaded(&path),
+                Err(e) => {
+                    self.manifest_failed = true;
+                    self.report.push(ManifestReport::failed(&path, &e));
+                    if console {
+                        println!("\x1b[31m❌ Error loading {}: {}\x1b[0m", path.display(), e);
                     }
-                };
-
-                {
-                    let _display_lock = display_mutex.lock().unwrap();
-                    let filled = BAR_WIDTH;
-                    let progress_bar = format!(
-                        "{}",
-                        "\x1b[44m \x1b[0m".repeat(filled)
-                    );
-                    let header = format!(
-                        "\r\x1b[1;34m[{:>3}/{:<3}]\x1b[0m \x1b[36m{:<40}\x1b[0m",
-                        file_number, total_files, file_check.path
-                    );
-                    println!(
-                        "{} [{}] 100.0%   {}{} {}\x1b[0m",
-                        header,
-                        progress_bar,
-                        status.color(),
-                        status.symbol(),
-                        status.text()
-                    );
-                    std::io::stdout().flush().unwrap();
-                }
-
-                VerificationResult {
-                    file_check: file_check.clone(),
-                    status,
                 }
-            })
-            .collect();
-
-        println!();
-
-        for result in results {
-            if let Some(file_check) = self.files.iter_mut().find(|f| f.path == result.file_check.path) {
-                file_check.status = Some(result.status);
             }
         }
-
-        let duration = start_time.elapsed();
-        println!("\nVerification completed in {:.2} seconds", duration.as_secs_f32());
     }
 
-    fn show_results(&self) {
-        let ok_count = self.files.iter().filter(|f| matches!(f.status, Some(FileStatus::Ok))).count();
-        let corrupted_count = self.files.iter().filter(|f| matches!(f.status, Some(FileStatus::Corrupted))).count();
-        let missing_count = self.files.iter().filter(|f| matches!(f.status, Some(FileStatus::Missing))).count();
-        let error_count = self.files.iter().filter(|f| matches!(f.status, Some(FileStatus::Error))).count();
-        let total = self.files.len();
-
-        println!("\n{}", "=".repeat(60));
-        println!("📊 VERIFICATION RESULTS");
-        println!("{}", "=".repeat(60));
-
-        if corrupted_count == 0 && missing_count == 0 && error_count == 0 {
-            println!("\x1b[32m✅ VERIFICATION SUCCESSFUL!\x1b[0m");
-            println!("\x1b[32mAll files are intact.\x1b[0m");
-        } else {
-            println!("\x1b[33m⚠️ PROBLEMS DETECTED\x1b[0m");
-            println!("\x1b[33mSome files need your attention.\x1b[0m");
-        }
-
-        println!("\n📈 Detailed Statistics:");
-        println!(" \x1b[32m✓ OK files         : {:>4}\x1b[0m", ok_count);
-        if corrupted_count > 0 {
-            println!(" \x1b[31m✗ Corrupted files  : {:>4}\x1b[0m", corrupted_count);
+    fn check_loaded(&mut self, path: &Path) {
+        if let Some(base_dir) = &self.args.base_dir {
+            self.verifier.base_path = base_dir.clone();
         }
-        if missing_count > 0 {
-            println!(" \x1b[33m? Missing files    : {:>4}\x1b[0m", missing_count);
+        if !self.args.check.is_empty() {
+            self.verifier.select_algos(&self.args.check);
         }
-        if error_count > 0 {
-            println!(" \x1b[31m! Read errors      : {:>4}\x1b[0m", error_count);
+        let console = self.args.console();
+        if console {
+            console::print_loaded(path, &self.verifier);
         }
-        println!(" 📁 Total files      : {:>4}", total);
 
-        if corrupted_count > 0 || missing_count > 0 || error_count > 0 {
-            println!("\n⚠️ Problematic files:");
-            for file_check in &self.files {
-                if let Some(status) = &file_check.status {
-                    match status {
-                        FileStatus::Corrupted => println!(" \x1b[31m✗ CORRUPTED\x1b[0m : {}", file_check.path),
-                        FileStatus::Missing => println!(" \x1b[33m? MISSING\x1b[0m   : {}", file_check.path),
-                        FileStatus::Error => println!(" \x1b[31m! ERROR\x1b[0m     : {}", file_check.path),
-                        _ => {}
+        let elapsed = self.verify_files(path);
+        if self.args.untracked {
+            match self.verifier.find_untracked(&self.untracked_ignore(path), &self.args.walk_options()) {
+                Ok(unmatched) => {
+                    for glob in unmatched {
+                        eprintln!("\x1b[33m⚠️ --include pattern `{}` matched no file\x1b[0m", glob);
                     }
                 }
+                Err(e) => {
+                    self.untracked_failed = true;
+                    eprintln!("\x1b[31m❌ Cannot look for untracked files: {}\x1b[0m", e);
+                }
             }
         }
-
-        println!("\n{}", "=".repeat(60));
-
-        if total > 0 {
-            let success_rate = (ok_count as f32 / total as f32) * 100.0;
-            if success_rate == 100.0 {
-                println!("\x1b[32m🎉 Success rate : {:.1}% - PERFECT!\x1b[0m", success_rate);
-            } else if success_rate >= 90.0 {
-                println!("\x1b[33m📊 Success rate : {:.1}% - Good\x1b[0m", success_rate);
-            } else {
-                println!("\x1b[31m📊 Success rate : {:.1}% - Attention required\x1b[0m", success_rate);
-            }
+        if console {
+            console::print_results(&self.verifier);
         }
+        let manifest_report = if self.args.wants_report() {
+            ManifestReport::from_verifier(path, &self.verifier, elapsed)
+        } else {
+            ManifestReport::totals_only(path, &self.verifier, elapsed)
+        };
+        self.report.push(manifest_report);
     }
 
-    fn run(&mut self) {
-        println!("🔐 XXHash3 File Verifier - Command Line Version");
-        println!("{}", "=".repeat(60));
-        println!();
-
-        match self.auto_load_hash_file() {
-            Ok(()) => {
-                println!("✓ Successfully loaded CRC.xxhash3 file");
-                println!("📂 Base directory: {}", self.base_path.display());
-                println!("📋 {} files to verify", self.files.len());
-
-                self.verify_files();
-                self.show_results();
-            }
-            Err(e) => {
-                println!("\x1b[31m❌ Error: {}\x1b[0m", e);
-                println!("\nMake sure a 'CRC.xxhash3' file exists in:");
-                println!("  - Current directory");
-                println!("  - Executable directory");
-            }
-        }
+    /// Files never reported as untracked: the manifest, its backup, stamps, hash cache,
+    /// verification journal and generation error log, this executable and the report file.
+    fn untracked_ignore(&self, manifest: &Path) -> Vec<PathBuf> {
+        let mut ignore = vec![
+            manifest.to_path_buf(),
+            backup_path(manifest),
+            Stamps::path_for(manifest),
+            manifest.with_file_name(CACHE_FILE_NAME),
+            with_suffix(manifest, VERIFY_JOURNAL_SUFFIX),
+            error_log_path(manifest),
+        ];
+        ignore.extend(std::env::current_exe());
+        ignore.extend(self.args.report_file.clone());
+        ignore
     }
-}
-
-#[allow(dead_code)]
-fn calculate_xxh3_hash(file_path: &Path) -> Result<String, std::io::Error> {
-    let mut file = File::open(file_path)?;
-    let mut hasher = Xxh3::new();
-    let mut buffer = [0u8; 8192];
 
-    loop {
-        let n = file.read(&mut buffer)?;
-        if n == 0 { break; }
-        hasher.update(&buffer[..n]);
+    fn exit_status(&self) -> VerifyExit {
+        if self.manifest_failed {
+            return VerifyExit::ManifestError;
+        }
+        match self.report.totals.exit_status() {
+            VerifyExit::Missing | VerifyExit::Untracked | VerifyExit::Ok if self.untracked_failed => VerifyExit::ReadError,
+            exit => exit,
+        }
     }
 
-    Ok(format!("{:016x}", hasher.digest()))
-}
-
-fn calculate_xxh3_hash_with_progress(
-    file_path: &Path,
-    mut progress_callback: impl FnMut(u64, u64) + Send + Sync,
-) -> Result<String, std::io::Error> {
-    let mut file = File::open(file_path)?;
-    let metadata = file.metadata()?;
-    let total_size = metadata.len();
-    let mut hasher = Xxh3::new();
-    let mut buffer = vec![0u8; 1024 * 1024]; // 1 MB buffer
-    let mut read_bytes = 0u64;
-
-    loop {
-        let n = file.read(&mut buffer)?;
-        if n == 0 {
-            break;
+    /// Emits the JSON report, if one was requested.
+    fn write_report(&mut self) -> io::Result<()> {
+        let exit = self.exit_status();
+        match &self.args.report_file {
+            Some(path) => self.report.write_json(exit, &mut File::create(path)?),
+            None if self.args.report == ReportFormat::Json => self.report.write_json(exit, &mut io::stdout().lock()),
+            None => Ok(()),
         }
-        hasher.update(&buffer[..n]);
-        read_bytes += n as u64;
-        progress_callback(read_bytes, total_size);
     }
-
-    Ok(format!("{:016x}", hasher.digest()))
 }
 
-fn calculate_md5_hash_with_progress(
-    file_path: &Path,
-    mut progress_callback: impl FnMut(u64, u64) + Send + Sync,
-) -> Result<String, std::io::Error> {
-    let mut file = File::open(file_path)?;
-    let metadata = file.metadata()?;
-    let total_size = metadata.len();
-    let mut context = md5::Context::new();
-    let mut buffer = vec![0u8; 1024 * 1024];
-    let mut read_bytes = 0u64;
-
-    loop {
-        let n = file.read(&mut buffer)?;
-        if n == 0 { break; }
-        context.consume(&buffer[..n]);
-        read_bytes += n as u64;
-        progress_callback(read_bytes, total_size);
+fn main() -> ExitCode {
+    let args = Args::parse();
+    // Before any thread is spawned, since threads inherit priorities
+    if (args.nice.is_some() || args.idle_io)
+        && let Err(e) = lower_priority(args.nice, args.idle_io)
+    {
+        eprintln!("Cannot lower priority: {e}");
+    }
+    if let Some(threads) = args.threads {
+        rayon::ThreadPoolBuilder::new().num_threads(threads).build_global().unwrap();
+    }
+    let devices = match DeviceLimits::new(&args.io_threads) {
+        Ok(devices) => devices,
+        Err(e) => Args::command().error(ErrorKind::InvalidValue, format!("--io-threads: {e}")).exit(),
+    };
+    let console = args.console();
+    let no_pause = args.no_pause || !console;
+
+    if console {
+        println!("XXHash3 File Verifier");
+        println!("=====================");
+        println!("This tool verifies file integrity using XXH3, XXH128, MD5, CRC32, SHA-1, SHA-256, SHA-512 or BLAKE3 hash values.");
+        println!("It expects a CRC.* file (CRC.xxhash3, CRC.sha256, ...) containing file paths and their expected hashes.");
     }
 
-    Ok(format!("{:032x}", context.finalize()))
-}
-
-fn calculate_crc32_hash_with_progress(
-    file_path: &Path,
-    mut progress_callback: impl FnMut(u64, u64) + Send + Sync,
-) -> Result<String, std::io::Error> {
-    let mut file = File::open(file_path)?;
-    let metadata = file.metadata()?;
-    let total_size = metadata.len();
-    let mut hasher = crc32fast::Hasher::new();
-    let mut buffer = vec![0u8; 1024 * 1024];
-    let mut read_bytes = 0u64;
+    let mut verifier = Xxh3VerifierCli::new(args, devices);
+    verifier.run();
+    if let Err(e) = verifier.write_report() {
+        eprintln!("\x1b[31m❌ Error writing report: {}\x1b[0m", e);
+    }
 
-    loop {
-        let n = file.read(&mut buffer)?;
-        if n == 0 { break; }
-        hasher.update(&buffer[..n]);
-        read_bytes += n as u64;
-        progress_callback(read_bytes, total_size);
+    if !no_pause {
+        console::wait_for_enter();
     }
 
-    Ok(format!("{:08x}", hasher.finalize()))
+    verifier.exit_status().into()
 }
-
-fn main() {
-    println!("XXHash3 File Verifier");
-    println!("=====================");
-    println!("This tool verifies file integrity using XXH3 hash values.");
-    println!("It expects a CRC.xxhash3 file containing file paths and their expected hashes.");
-    
-    let mut verifier = Xxh3VerifierCli::new();
-    verifier.run();
-
-    println!("\nPress Enter to exit...");
-    let mut input = String::new();
-    stdin().read_line(&mut input).unwrap();
-}
\ No newline at end of file